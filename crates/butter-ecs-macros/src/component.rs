@@ -0,0 +1,87 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{DeriveInput, Error, LitStr, Path, Result};
+
+use crate::path;
+
+#[derive(Default)]
+struct Attributes {
+    storage: Option<Path>,
+    on_add: Option<Path>,
+    on_remove: Option<Path>,
+}
+
+impl Attributes {
+    fn parse(input: &DeriveInput) -> Result<Self> {
+        let mut attributes = Self::default();
+
+        for attr in input
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("component"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("storage") {
+                    let storage: LitStr = meta.value()?.parse()?;
+                    let variant = match storage.value().as_str() {
+                        "dense" => format_ident!("Dense"),
+                        "sparse_set" => format_ident!("SparseSet"),
+                        _ => {
+                            return Err(Error::new_spanned(
+                                storage,
+                                "unknown storage, expected \"dense\" or \"sparse_set\"",
+                            ))
+                        }
+                    };
+                    attributes.storage = Some(variant.into());
+                } else if meta.path.is_ident("on_add") {
+                    attributes.on_add = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("on_remove") {
+                    attributes.on_remove = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("unknown component attribute"));
+                }
+
+                Ok(())
+            })?;
+        }
+
+        Ok(attributes)
+    }
+}
+
+pub(crate) fn derive(input: &DeriveInput) -> Result<TokenStream> {
+    let attributes = Attributes::parse(input)?;
+
+    let ecs = path::ecs();
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let storage = attributes.storage.map(|variant| {
+        quote! {
+            const STORAGE: #ecs::StorageType = #ecs::StorageType::#variant;
+        }
+    });
+    let on_add = attributes.on_add.map(|on_add| {
+        quote! {
+            fn on_add(ecs: &mut #ecs::Ecs, entity_index: #ecs::EntityIndex) {
+                #on_add(ecs, entity_index);
+            }
+        }
+    });
+    let on_remove = attributes.on_remove.map(|on_remove| {
+        quote! {
+            fn on_remove(ecs: &mut #ecs::Ecs, entity_index: #ecs::EntityIndex) {
+                #on_remove(ecs, entity_index);
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics #ecs::Component for #name #ty_generics #where_clause {
+            #storage
+            #on_add
+            #on_remove
+        }
+    })
+}
@@ -0,0 +1,34 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Index, Member, Result};
+
+use crate::path;
+
+pub(crate) fn derive(input: &DeriveInput) -> Result<TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            input,
+            "EntityDefinition can only be derived for structs",
+        ));
+    };
+
+    let ecs = path::ecs();
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let members = data
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index::from(i)),
+        });
+
+    Ok(quote! {
+        impl #impl_generics #ecs::EntityDefinition for #name #ty_generics #where_clause {
+            fn store_component(self, ecs: &mut #ecs::Ecs, index: usize) {
+                #(#ecs::EntityDefinition::store_component(self.#members, ecs, index);)*
+            }
+        }
+    })
+}
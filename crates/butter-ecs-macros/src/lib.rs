@@ -0,0 +1,47 @@
+#![warn(clippy::pedantic)]
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+mod component;
+mod entity_definition;
+mod path;
+mod system_param;
+
+/// Implements `Component`, configured by an optional `component` attribute:
+///
+/// - `storage = "dense"` or `storage = "sparse_set"` selects the `StorageType`
+/// - `on_add = path` and `on_remove = path` set the hooks, which are functions
+///   taking `&mut Ecs` and the `EntityIndex`
+#[proc_macro_derive(Component, attributes(component))]
+pub fn derive_component(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    component::derive(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements `EntityDefinition` for a struct whose fields are all entity definitions
+#[proc_macro_derive(EntityDefinition)]
+pub fn derive_entity_definition(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    entity_definition::derive(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Same as `EntityDefinition`
+#[proc_macro_derive(Bundle)]
+pub fn derive_bundle(input: TokenStream) -> TokenStream {
+    derive_entity_definition(input)
+}
+
+/// Implements `system::Parameter` for a struct whose fields are all parameters,
+/// letting a system take them as a single argument
+#[proc_macro_derive(SystemParam)]
+pub fn derive_system_param(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    system_param::derive(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,24 @@
+use proc_macro2::{Span, TokenStream};
+use proc_macro_crate::{crate_name, FoundCrate};
+use quote::quote;
+use syn::Ident;
+
+/// Returns the path to `butter_ecs` as seen from the crate using the derive,
+/// whether it depends on `butter-ecs` directly or only on `butter`
+pub(crate) fn ecs() -> TokenStream {
+    match crate_name("butter-ecs") {
+        Ok(FoundCrate::Itself) => quote!(::butter_ecs),
+        Ok(FoundCrate::Name(name)) => {
+            let name = Ident::new(&name, Span::call_site());
+            quote!(::#name)
+        }
+        Err(_) => {
+            if let Ok(FoundCrate::Name(name)) = crate_name("butter") {
+                let name = Ident::new(&name, Span::call_site());
+                quote!(::#name::ecs)
+            } else {
+                quote!(::butter::ecs)
+            }
+        }
+    }
+}
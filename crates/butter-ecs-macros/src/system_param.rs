@@ -0,0 +1,122 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    parse_quote, visit_mut::VisitMut, Data, DeriveInput, Error, Index, Lifetime, Member, Result,
+    Type,
+};
+
+use crate::path;
+
+/// Replaces every occurrence of a lifetime by another one
+struct ReplaceLifetime<'a> {
+    from: &'a Lifetime,
+    to: Lifetime,
+}
+
+impl VisitMut for ReplaceLifetime<'_> {
+    fn visit_lifetime_mut(&mut self, lifetime: &mut Lifetime) {
+        if lifetime == self.from {
+            *lifetime = self.to.clone();
+        }
+    }
+}
+
+fn replace_lifetime(ty: &Type, from: Option<&Lifetime>, to: &str) -> Type {
+    let mut ty = ty.clone();
+    if let Some(from) = from {
+        ReplaceLifetime {
+            from,
+            to: Lifetime::new(to, Span::call_site()),
+        }
+        .visit_type_mut(&mut ty);
+    }
+    ty
+}
+
+pub(crate) fn derive(input: &DeriveInput) -> Result<TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            input,
+            "SystemParam can only be derived for structs",
+        ));
+    };
+
+    let mut lifetimes = input.generics.lifetimes();
+    let lifetime = lifetimes.next().map(|param| &param.lifetime);
+    if let Some(extra_lifetime) = lifetimes.next() {
+        return Err(Error::new_spanned(
+            extra_lifetime,
+            "SystemParam can only be derived for structs with at most one lifetime",
+        ));
+    }
+
+    let ecs = path::ecs();
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let self_ty: Type = parse_quote!(#name #ty_generics);
+    let item_ty = replace_lifetime(&self_ty, lifetime, "'__ecs");
+
+    let field_tys: Vec<_> = data
+        .fields
+        .iter()
+        .map(|field| replace_lifetime(&field.ty, lifetime, "'static"))
+        .collect();
+    let states: Vec<_> = (0..field_tys.len())
+        .map(|i| format_ident!("state_{}", i))
+        .collect();
+    let fields = data
+        .fields
+        .iter()
+        .zip(&field_tys)
+        .zip(&states)
+        .enumerate()
+        .map(|(i, ((field, ty), state))| {
+            let member = match &field.ident {
+                Some(ident) => Member::Named(ident.clone()),
+                None => Member::Unnamed(Index::from(i)),
+            };
+            quote! {
+                #member: <#ty as #ecs::system::Parameter>::fetch(#state, ecs)
+            }
+        });
+
+    Ok(quote! {
+        impl #impl_generics #ecs::system::Parameter for #self_ty #where_clause {
+            type Type<'__ecs> = #item_ty;
+            type State = (#(<#field_tys as #ecs::system::Parameter>::State,)*);
+            const IS_SEND: bool = true #(&& <#field_tys as #ecs::system::Parameter>::IS_SEND)*;
+
+            fn init_state() -> Self::State {
+                (#(<#field_tys as #ecs::system::Parameter>::init_state(),)*)
+            }
+
+            fn access(access: &mut #ecs::system::Access) {
+                #(<#field_tys as #ecs::system::Parameter>::access(access);)*
+            }
+
+            #[allow(unused_unsafe)]
+            unsafe fn fetch<'__ecs>(
+                state: &'__ecs mut Self::State,
+                ecs: &'__ecs #ecs::Ecs,
+            ) -> Self::Type<'__ecs> {
+                let (#(#states,)*) = state;
+                // SAFETY:
+                // The accesses of the fields are part of the access of the
+                // struct
+                unsafe {
+                    #name {
+                        #(#fields,)*
+                    }
+                }
+            }
+
+            fn queue_commands(
+                state: &mut Self::State,
+                command_queue: &mut #ecs::commands::CommandQueue,
+            ) {
+                let (#(#states,)*) = state;
+                #(<#field_tys as #ecs::system::Parameter>::queue_commands(#states, command_queue);)*
+            }
+        }
+    })
+}
@@ -0,0 +1,77 @@
+use butter_ecs::{Component, Ecs};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+const BULLET_COUNT: usize = 10_000;
+
+#[derive(Component)]
+struct Bullet;
+
+#[allow(dead_code)]
+#[derive(Component)]
+struct Position(f32, f32);
+
+#[allow(dead_code)]
+#[derive(Component)]
+struct Velocity(f32, f32);
+
+fn bullet(i: usize) -> (Bullet, Position, Velocity) {
+    #[allow(clippy::cast_precision_loss)]
+    let x = i as f32;
+    (Bullet, Position(x, 0.0), Velocity(0.0, 1.0))
+}
+
+fn spawn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spawn 10k bullets");
+
+    group.bench_function("insert", |b| {
+        b.iter(|| {
+            let mut ecs = Ecs::new();
+            for i in 0..BULLET_COUNT {
+                ecs.insert(bullet(i));
+            }
+            black_box(ecs)
+        });
+    });
+
+    group.bench_function("reserve then insert", |b| {
+        b.iter(|| {
+            let mut ecs = Ecs::new();
+            ecs.insert(bullet(0));
+            ecs.reserve(BULLET_COUNT - 1);
+            for i in 1..BULLET_COUNT {
+                ecs.insert(bullet(i));
+            }
+            black_box(ecs)
+        });
+    });
+
+    group.bench_function("spawn_batch", |b| {
+        b.iter(|| {
+            let mut ecs = Ecs::new();
+            ecs.spawn_batch((0..BULLET_COUNT).map(bullet));
+            black_box(ecs)
+        });
+    });
+
+    group.finish();
+}
+
+fn despawn(c: &mut Criterion) {
+    c.bench_function("despawn_batch 10k bullets", |b| {
+        b.iter_batched(
+            || {
+                let mut ecs = Ecs::new();
+                let bullets = ecs.spawn_batch((0..BULLET_COUNT).map(bullet));
+                (ecs, bullets)
+            },
+            |(mut ecs, bullets)| {
+                ecs.despawn_batch(bullets);
+                black_box(ecs)
+            },
+            BatchSize::LargeInput,
+        );
+    });
+}
+
+criterion_group!(benches, spawn, despawn);
+criterion_main!(benches);
@@ -1,4 +1,6 @@
-use crate::{Ecs, EntityDefinition};
+use std::ops::{Deref, DerefMut};
+
+use crate::{system::Parameter, Ecs, EntityDefinition};
 
 pub struct CommandQueue {
     commands: Vec<Box<dyn Command>>,
@@ -18,6 +20,14 @@ impl CommandQueue {
             .push(Box::new(InsertEntityCommand::new(entity_definition)));
     }
 
+    /// Queues a command, like a closure taking the `Ecs`
+    pub fn push<C>(&mut self, command: C)
+    where
+        C: 'static + Command,
+    {
+        self.commands.push(Box::new(command));
+    }
+
     pub fn extend<I>(&mut self, iter: I)
     where
         I: IntoIterator<Item = Box<dyn Command>>,
@@ -25,7 +35,7 @@ impl CommandQueue {
         self.commands.extend(iter);
     }
 
-    pub fn drain(&mut self) -> std::vec::Drain<Box<dyn Command>> {
+    pub fn drain(&mut self) -> std::vec::Drain<'_, Box<dyn Command>> {
         self.commands.drain(..)
     }
 }
@@ -36,10 +46,67 @@ impl Default for CommandQueue {
     }
 }
 
+/// Gives a system a queue of commands, executed by the `Ecs` once the system
+/// has run
+///
+/// ```
+/// # use butter_ecs::{commands::Commands, Component};
+/// # #[derive(Component)]
+/// # struct Enemy;
+/// fn spawn_enemy(mut commands: Commands) {
+///     commands.insert(Enemy);
+/// }
+/// ```
+pub struct Commands<'s> {
+    command_queue: &'s mut CommandQueue,
+}
+
+impl Deref for Commands<'_> {
+    type Target = CommandQueue;
+
+    fn deref(&self) -> &Self::Target {
+        self.command_queue
+    }
+}
+
+impl DerefMut for Commands<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.command_queue
+    }
+}
+
+impl Parameter for Commands<'_> {
+    type Type<'s> = Commands<'s>;
+    type State = CommandQueue;
+
+    fn init_state() -> Self::State {
+        CommandQueue::new()
+    }
+
+    unsafe fn fetch<'a>(state: &'a mut Self::State, _ecs: &'a Ecs) -> Self::Type<'a> {
+        Commands {
+            command_queue: state,
+        }
+    }
+
+    fn queue_commands(state: &mut Self::State, command_queue: &mut CommandQueue) {
+        command_queue.extend(state.drain());
+    }
+}
+
 pub trait Command {
     fn execute(self: Box<Self>, ecs: &mut Ecs);
 }
 
+impl<F> Command for F
+where
+    F: FnOnce(&mut Ecs),
+{
+    fn execute(self: Box<Self>, ecs: &mut Ecs) {
+        (*self)(ecs);
+    }
+}
+
 pub struct InsertEntityCommand<ED>
 where
     ED: EntityDefinition,
@@ -0,0 +1,285 @@
+use std::{any::Any, borrow::Cow, fmt};
+
+use web_time::Duration;
+
+use crate::{Ecs, EntityIndex, StorageType};
+
+/// Describes the components of a given type stored in an [`Ecs`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComponentInfo {
+    pub name: &'static str,
+    pub storage: StorageType,
+    /// Number of entities having this component
+    pub count: usize,
+    /// Memory used by the store, in bytes
+    pub memory: usize,
+}
+
+/// Time taken by a system during the last call to [`Ecs::run_systems`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SystemTiming {
+    pub name: Cow<'static, str>,
+    pub duration: Duration,
+}
+
+/// A panic caught by [`Ecs::run_systems`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SystemPanic {
+    pub name: Cow<'static, str>,
+    /// Message of the panic, if it was a string
+    pub message: Option<String>,
+}
+
+impl SystemPanic {
+    pub(crate) fn new(name: Cow<'static, str>, payload: &(dyn Any + Send)) -> Self {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(ToString::to_string)
+            .or_else(|| payload.downcast_ref::<String>().cloned());
+        Self { name, message }
+    }
+}
+
+/// An error returned by a system, see [`Report`](crate::system::Report)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SystemError {
+    pub name: Cow<'static, str>,
+    pub message: String,
+}
+
+impl fmt::Display for SystemError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "System {} failed: {}", self.name, self.message)
+    }
+}
+
+impl fmt::Display for SystemPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "System {} panicked", self.name)?;
+        if let Some(message) = &self.message {
+            write!(f, ": {message}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Ecs {
+    /// Lists the type of components stored, sorted by name
+    #[must_use]
+    pub fn component_infos(&self) -> Vec<ComponentInfo> {
+        let mut component_infos: Vec<_> = self
+            .component_stores
+            .iter()
+            .map(|store| ComponentInfo {
+                name: store.descriptor.name,
+                storage: store.descriptor.storage,
+                count: store.count,
+                memory: store.memory(),
+            })
+            .collect();
+        component_infos.sort_unstable_by_key(|component_info| component_info.name);
+        component_infos
+    }
+
+    /// Lists the name of the components of an entity, sorted alphabetically
+    #[must_use]
+    pub fn entity_components(&self, entity_index: EntityIndex) -> Vec<&'static str> {
+        if !self.contains(entity_index) {
+            return vec![];
+        }
+
+        let mut names: Vec<_> = self
+            .component_stores
+            .iter()
+            .filter(|store| store.contains(entity_index.index))
+            .map(|store| store.descriptor.name)
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Time taken by each run of the systems given to [`Ecs::run_systems`]
+    /// since the last call to [`Ecs::clear_system_diagnostics`], in the order
+    /// they ran
+    #[must_use]
+    pub fn system_timings(&self) -> &[SystemTiming] {
+        &self.system_timings
+    }
+
+    /// Panics caught by [`Ecs::run_systems`] with the
+    /// [`Continue`](crate::system::PanicPolicy::Continue) policy since the
+    /// last call to [`Ecs::clear_system_diagnostics`]
+    #[must_use]
+    pub fn system_panics(&self) -> &[SystemPanic] {
+        &self.system_panics
+    }
+
+    /// Errors returned by the [`Reported`](crate::system::Reported) systems
+    /// since the last call to [`Ecs::clear_system_diagnostics`]
+    #[must_use]
+    pub fn system_errors(&self) -> &[SystemError] {
+        &self.system_errors
+    }
+
+    /// Forgets the timings, panics and errors of the systems which already
+    /// ran, which is usually done at the start of each frame
+    pub fn clear_system_diagnostics(&mut self) {
+        self.system_timings.clear();
+        self.system_panics.clear();
+        self.system_errors.clear();
+    }
+}
+
+impl fmt::Debug for Ecs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ecs")
+            .field("entity_count", &self.entity_count())
+            .field("components", &self.component_infos())
+            .field("system_timings", &self.system_timings)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        commands::Commands,
+        query::Query,
+        system::{Into, Local, PanicPolicy, System},
+        Component,
+    };
+
+    #[derive(Component)]
+    struct Player;
+    #[derive(Component)]
+    struct Health(u16);
+    #[derive(Component)]
+    #[component(storage = "sparse_set")]
+    struct Selected;
+
+    #[test]
+    fn component_infos() {
+        let mut ecs = Ecs::new();
+        let player = ecs.insert((Player, Health(10), Selected));
+        ecs.insert((Health(5), Selected));
+        ecs.delete(player);
+        // Removes the store of the players, emptied by the deletion
+        ecs.shrink_to_fit();
+
+        let component_infos = ecs.component_infos();
+        let names: Vec<_> = component_infos.iter().map(|info| info.name).collect();
+        assert_eq!(
+            names,
+            [
+                std::any::type_name::<Health>(),
+                std::any::type_name::<Selected>(),
+            ]
+        );
+
+        let counts: Vec<_> = component_infos.iter().map(|info| info.count).collect();
+        assert_eq!(counts, [1, 1]);
+        assert_eq!(component_infos[1].storage, StorageType::SparseSet);
+        assert!(component_infos[0].memory >= 2 * std::mem::size_of::<Health>());
+    }
+
+    #[test]
+    fn entity_components() {
+        let mut ecs = Ecs::new();
+        let player = ecs.insert((Player, Health(10)));
+        assert_eq!(
+            ecs.entity_components(player),
+            [
+                std::any::type_name::<Health>(),
+                std::any::type_name::<Player>(),
+            ]
+        );
+
+        ecs.delete(player);
+        assert!(ecs.entity_components(player).is_empty());
+    }
+
+    #[test]
+    fn system_timings() {
+        fn heal(query: Query<&mut Health>) {
+            for health in query {
+                health.0 += 1;
+            }
+        }
+
+        let mut ecs = Ecs::new();
+        ecs.run_systems(&mut [
+            Box::new(heal.into_system()),
+            Box::new((|| {}).named("idle")),
+        ]);
+        let names: Vec<_> = ecs
+            .system_timings()
+            .iter()
+            .map(|timing| timing.name.as_ref())
+            .collect();
+        assert_eq!(names, [std::any::type_name_of_val(&heal), "idle"]);
+    }
+
+    #[test]
+    fn system_panics_continue() {
+        fn spawn(mut commands: Commands) {
+            commands.insert((Player,));
+        }
+
+        fn crash(mut commands: Commands) {
+            commands.insert((Player,));
+            panic!("out of health");
+        }
+
+        let mut ecs = Ecs::new();
+        ecs.set_panic_policy(PanicPolicy::Continue);
+        ecs.run_systems(&mut [Box::new(crash.into_system()), Box::new(spawn.into_system())]);
+        assert_eq!(ecs.entity_count(), 1);
+        assert_eq!(
+            ecs.system_panics(),
+            [SystemPanic {
+                name: std::any::type_name_of_val(&crash).into(),
+                message: Some("out of health".to_string()),
+            }]
+        );
+
+        ecs.clear_system_diagnostics();
+        ecs.run_systems(&mut [Box::new(spawn.into_system())]);
+        assert!(ecs.system_panics().is_empty());
+    }
+
+    #[test]
+    fn system_panics_discard_commands() {
+        fn crash_once(mut commands: Commands, mut crashed: Local<bool>) {
+            commands.insert((Player,));
+            if !*crashed {
+                *crashed = true;
+                panic!("out of health");
+            }
+        }
+
+        let mut ecs = Ecs::new();
+        ecs.set_panic_policy(PanicPolicy::Continue);
+        let mut systems: [Box<dyn System>; 1] = [Box::new(crash_once.into_system())];
+        ecs.run_systems(&mut systems);
+        assert_eq!(ecs.entity_count(), 0);
+        ecs.run_systems(&mut systems);
+        assert_eq!(ecs.entity_count(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "System crash panicked: out of health")]
+    fn system_panics_abort() {
+        let mut ecs = Ecs::new();
+        ecs.run_systems(&mut [Box::new((|| panic!("out of health")).named("crash"))]);
+    }
+
+    #[test]
+    fn debug_dump() {
+        let mut ecs = Ecs::new();
+        ecs.insert((Player, Health(10)));
+        let dump = format!("{ecs:?}");
+        assert!(dump.contains("entity_count: 1"));
+        assert!(dump.contains(std::any::type_name::<Health>()));
+    }
+}
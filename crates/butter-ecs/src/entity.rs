@@ -0,0 +1,178 @@
+use crate::{Component, Ecs, EntityDefinition, EntityIndex};
+
+/// Builds an entity one component at a time, see [`Ecs::spawn`]
+pub struct EntityBuilder<'ecs> {
+    ecs: &'ecs mut Ecs,
+    entity_index: EntityIndex,
+}
+
+impl<'ecs> EntityBuilder<'ecs> {
+    pub(crate) fn new(ecs: &'ecs mut Ecs, entity_index: EntityIndex) -> Self {
+        Self { ecs, entity_index }
+    }
+
+    pub fn with<ED>(&mut self, entity_definition: ED) -> &mut Self
+    where
+        ED: EntityDefinition,
+    {
+        entity_definition.store_component(self.ecs, self.entity_index.index);
+        self
+    }
+
+    #[must_use]
+    pub fn id(&self) -> EntityIndex {
+        self.entity_index
+    }
+}
+
+/// Gives mutable access to the components of a single entity, see [`Ecs::entity_mut`]
+pub struct EntityMut<'ecs> {
+    ecs: &'ecs mut Ecs,
+    entity_index: EntityIndex,
+}
+
+impl<'ecs> EntityMut<'ecs> {
+    pub(crate) fn new(ecs: &'ecs mut Ecs, entity_index: EntityIndex) -> Self {
+        Self { ecs, entity_index }
+    }
+
+    #[must_use]
+    pub fn id(&self) -> EntityIndex {
+        self.entity_index
+    }
+
+    #[must_use]
+    pub fn get<C: Component>(&self) -> Option<&C> {
+        self.ecs.component::<C>(self.entity_index)
+    }
+
+    #[must_use]
+    pub fn get_mut<C: Component>(&mut self) -> Option<&mut C> {
+        self.ecs.component_mut::<C>(self.entity_index)
+    }
+
+    /// Adds the given components, replacing the ones the entity already has
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the entity has been despawned
+    pub fn insert<ED>(&mut self, entity_definition: ED) -> &mut Self
+    where
+        ED: EntityDefinition,
+    {
+        self.assert_alive();
+        entity_definition.store_component(self.ecs, self.entity_index.index);
+        self
+    }
+
+    /// # Panics
+    ///
+    /// Will panic if the entity has been despawned
+    pub fn remove<C: Component>(&mut self) -> &mut Self {
+        self.assert_alive();
+        self.ecs.remove_component::<C>(self.entity_index.index);
+        self
+    }
+
+    /// Deletes the entity, the handle can't be used to modify it anymore
+    pub fn despawn(&mut self) {
+        self.ecs.delete(self.entity_index);
+    }
+
+    fn assert_alive(&self) {
+        assert!(
+            self.ecs.contains(self.entity_index),
+            "Entity {} (generation {}) has been despawned",
+            self.entity_index.index,
+            self.entity_index.generation
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Eq, Component)]
+    struct Player;
+    #[derive(Debug, PartialEq, Eq, Component)]
+    struct Health(i16);
+    #[derive(Debug, PartialEq, Eq, Component)]
+    struct Poisoned;
+
+    #[test]
+    fn spawn_with_components() {
+        let mut ecs = Ecs::new();
+        let player = ecs.spawn().with(Player).with((Health(10), Poisoned)).id();
+
+        assert_eq!(ecs.entity_count(), 1);
+        assert_eq!(ecs.component::<Player>(player), Some(&Player));
+        assert_eq!(ecs.component::<Health>(player), Some(&Health(10)));
+        assert_eq!(ecs.component::<Poisoned>(player), Some(&Poisoned));
+    }
+
+    #[test]
+    fn spawn_empty_entity() {
+        let mut ecs = Ecs::new();
+        let entity = ecs.spawn().id();
+        assert!(ecs.contains(entity));
+        assert_eq!(ecs.entity_count(), 1);
+    }
+
+    #[test]
+    fn entity_mut_insert_and_remove() {
+        let mut ecs = Ecs::new();
+        let player = ecs.insert((Player, Poisoned));
+
+        let mut entity = ecs.entity_mut(player);
+        entity.insert(Health(10)).remove::<Poisoned>();
+        assert_eq!(entity.get::<Health>(), Some(&Health(10)));
+
+        assert_eq!(ecs.component::<Player>(player), Some(&Player));
+        assert_eq!(ecs.component::<Health>(player), Some(&Health(10)));
+        assert_eq!(ecs.component::<Poisoned>(player), None);
+    }
+
+    #[test]
+    fn entity_mut_insert_replaces_component() {
+        let mut ecs = Ecs::new();
+        let player = ecs.insert((Player, Health(10)));
+
+        let mut entity = ecs.entity_mut(player);
+        entity.insert(Health(5));
+        entity.get_mut::<Health>().unwrap().0 -= 1;
+
+        assert_eq!(ecs.component::<Health>(player), Some(&Health(4)));
+    }
+
+    #[test]
+    fn entity_mut_despawn() {
+        let mut ecs = Ecs::new();
+        let player = ecs.insert((Player, Health(10)));
+
+        ecs.entity_mut(player).insert(Poisoned).despawn();
+
+        assert!(!ecs.contains(player));
+        assert_eq!(ecs.entity_count(), 0);
+        assert_eq!(ecs.query::<&Poisoned>().count(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not exist")]
+    fn entity_mut_deleted_entity() {
+        let mut ecs = Ecs::new();
+        let player = ecs.insert((Player,));
+        ecs.delete(player);
+        let _ = ecs.entity_mut(player);
+    }
+
+    #[test]
+    #[should_panic(expected = "has been despawned")]
+    fn entity_mut_insert_after_despawn() {
+        let mut ecs = Ecs::new();
+        let player = ecs.insert((Player,));
+        let mut entity = ecs.entity_mut(player);
+        entity.despawn();
+        entity.insert(Health(10));
+    }
+}
@@ -0,0 +1,237 @@
+use crate::{
+    resource::ResMut,
+    system::{Access, Parameter},
+    Ecs,
+};
+
+/// A resource holding the events of type `E`, each one being readable during
+/// the frame it was sent and the next one
+///
+/// [`Events::update`] has to run once per frame to discard the old events,
+/// which the engine does for the events it registers.
+pub struct Events<E> {
+    previous: Vec<E>,
+    current: Vec<E>,
+    /// Id of the first event of `previous`, the following ones having the
+    /// next ids
+    previous_start: usize,
+}
+
+impl<E> Events<E> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            previous: vec![],
+            current: vec![],
+            previous_start: 0,
+        }
+    }
+
+    pub fn send(&mut self, event: E) {
+        self.current.push(event);
+    }
+
+    /// Discards the events sent before the last update
+    pub fn update(&mut self) {
+        self.previous_start += self.previous.len();
+        self.previous = std::mem::take(&mut self.current);
+    }
+
+    /// A system calling [`Events::update`]
+    pub fn update_system(mut events: ResMut<Self>)
+    where
+        E: Send + Sync + 'static,
+    {
+        events.update();
+    }
+
+    pub fn clear(&mut self) {
+        self.update();
+        self.update();
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn next_id(&self) -> usize {
+        self.previous_start + self.len()
+    }
+
+    /// Iterates over the events whose id is `id` or more
+    fn iter_from(&self, id: usize) -> impl Iterator<Item = &E> {
+        let skipped = id.saturating_sub(self.previous_start);
+        self.previous.iter().chain(&self.current).skip(skipped)
+    }
+}
+
+impl<E> Default for Events<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Gives a system the events of type `E` it hasn't read yet
+///
+/// ```
+/// # use butter_ecs::event::EventReader;
+/// struct Collision(u32, u32);
+///
+/// fn play_collision_sounds(mut collisions: EventReader<Collision>) {
+///     for collision in collisions.read() {
+///         println!("{} hit {}", collision.0, collision.1);
+///     }
+/// }
+/// ```
+///
+/// # Panics
+///
+/// The system will panic when run if the [`Events`] resource doesn't exist
+pub struct EventReader<'ecs, E> {
+    events: &'ecs Events<E>,
+    next_id: &'ecs mut usize,
+}
+
+impl<'ecs, E> EventReader<'ecs, E> {
+    /// Iterates over the events sent since the last time the system read
+    /// them, marking them as read
+    pub fn read(&mut self) -> impl Iterator<Item = &'ecs E> {
+        let id = *self.next_id;
+        *self.next_id = self.events.next_id();
+        self.events.iter_from(id)
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self.next_id >= self.events.next_id()
+    }
+}
+
+impl<E> Parameter for EventReader<'_, E>
+where
+    E: Send + Sync + 'static,
+{
+    type Type<'ecs> = EventReader<'ecs, E>;
+    type State = usize;
+
+    fn init_state() -> Self::State {
+        0
+    }
+
+    fn access(access: &mut Access) {
+        access.read_resource::<Events<E>>();
+    }
+
+    unsafe fn fetch<'a>(state: &'a mut Self::State, ecs: &'a Ecs) -> Self::Type<'a> {
+        let events = ecs
+            .resource::<Events<E>>()
+            .unwrap_or_else(|| panic!("Event {} is not registered", std::any::type_name::<E>()));
+        EventReader {
+            events,
+            next_id: state,
+        }
+    }
+}
+
+/// Lets a system send events of type `E`, see [`EventReader`]
+///
+/// # Panics
+///
+/// The system will panic when run if the [`Events`] resource doesn't exist
+pub struct EventWriter<'ecs, E> {
+    events: &'ecs mut Events<E>,
+}
+
+impl<E> EventWriter<'_, E> {
+    pub fn send(&mut self, event: E) {
+        self.events.send(event);
+    }
+}
+
+impl<E> Parameter for EventWriter<'_, E>
+where
+    E: Send + Sync + 'static,
+{
+    type Type<'ecs> = EventWriter<'ecs, E>;
+    type State = ();
+
+    fn init_state() -> Self::State {}
+
+    fn access(access: &mut Access) {
+        access.write_resource::<Events<E>>();
+    }
+
+    unsafe fn fetch<'a>(_state: &'a mut Self::State, ecs: &'a Ecs) -> Self::Type<'a> {
+        // SAFETY:
+        // The caller ensures that the events aren't borrowed anywhere else
+        let events = unsafe { ecs.resource_mut_unchecked::<Events<E>>() }
+            .unwrap_or_else(|| panic!("Event {} is not registered", std::any::type_name::<E>()));
+        EventWriter { events }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::Into;
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Hit(u32);
+
+    #[test]
+    fn events_last_two_updates() {
+        let mut events = Events::new();
+        events.send(Hit(1));
+        events.update();
+        events.send(Hit(2));
+        assert_eq!(events.iter_from(0).collect::<Vec<_>>(), [&Hit(1), &Hit(2)]);
+
+        events.update();
+        assert_eq!(events.iter_from(0).collect::<Vec<_>>(), [&Hit(2)]);
+        assert_eq!(events.iter_from(2).count(), 0);
+
+        events.clear();
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn systems_with_events() {
+        fn send_hits(mut hits: EventWriter<Hit>, mut count: crate::system::Local<u32>) {
+            *count += 1;
+            hits.send(Hit(*count));
+        }
+
+        fn sum_hits(mut hits: EventReader<Hit>) -> u32 {
+            hits.read().map(|hit| hit.0).sum()
+        }
+
+        let mut ecs = Ecs::new();
+        ecs.insert_resource(Events::<Hit>::new());
+        let mut update = Events::<Hit>::update_system.into_system();
+        let mut send = send_hits.into_system();
+        let mut sum = sum_hits.into_system();
+
+        ecs.run_single_system(&mut send);
+        ecs.run_single_system(&mut send);
+        assert_eq!(ecs.run_single_system(&mut sum), 3);
+        assert_eq!(ecs.run_single_system(&mut sum), 0);
+
+        // Events sent after a reader ran are read during the next frame
+        ecs.run_single_system(&mut update);
+        ecs.run_single_system(&mut send);
+        ecs.run_single_system(&mut update);
+        assert_eq!(ecs.run_single_system(&mut sum), 3);
+
+        // And lost if it doesn't run for two updates
+        ecs.run_single_system(&mut send);
+        ecs.run_single_system(&mut update);
+        ecs.run_single_system(&mut update);
+        assert_eq!(ecs.run_single_system(&mut sum), 0);
+    }
+}
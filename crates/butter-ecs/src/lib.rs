@@ -1,20 +1,76 @@
 #![warn(clippy::pedantic)]
 use commands::CommandQueue;
 
-use crate::bitset::Bitset;
-use std::{alloc::Layout, any::TypeId, collections::HashMap, ptr::NonNull};
+// Lets the derive macros refer to `::butter_ecs` from inside this crate too
+extern crate self as butter_ecs;
 
-use self::system::System;
+use crate::bitset::Bitset;
+use std::{
+    alloc::Layout,
+    any::TypeId,
+    collections::HashMap,
+    ops::Range,
+    ptr::NonNull,
+    sync::atomic::{AtomicU64, Ordering},
+    thread::ThreadId,
+};
+use web_time::Instant;
+
+use self::{
+    diagnostics::{SystemError, SystemPanic, SystemTiming},
+    entity::{EntityBuilder, EntityMut},
+    resource::{NonSendCell, ResourceCell, ResourceCloneFn},
+    stores::ComponentStores,
+    system::{PanicPolicy, System},
+};
+
+pub use butter_ecs_macros::{Bundle, Component, EntityDefinition, SystemParam};
 
 mod bitset;
 pub mod commands;
+pub mod diagnostics;
+pub mod entity;
+pub mod event;
 pub mod query;
+pub mod resource;
+pub mod snapshot;
+mod stores;
 pub mod system;
 
+/// The entities and their components, along with the resources
+///
+/// # Determinism
+///
+/// Given the same sequence of calls, an `Ecs` behaves identically on every
+/// run and platform, which lockstep multiplayer and replays rely on:
+/// - the indices of deleted entities are reused last in, first out, with
+///   their generation incremented, before any new index is allocated
+/// - queries and [`Ecs::entities`] go through the entities in increasing
+///   index order
+/// - the types of components are visited in the order their stores were
+///   created, so deleting an entity runs the `on_remove` hooks and drops its
+///   components in that order. A store is created when a component of its
+///   type is stored while there is none. It stays at its place in the order
+///   once it is empty, until [`Ecs::shrink_to_fit`] or [`Ecs::restore`]
+///   removes it, a new store then going last.
 pub struct Ecs {
     next_index: usize,
     deleted_entities_indices: Vec<EntityIndex>,
-    component_stores: HashMap<TypeId, ComponentStore>,
+    generations: Vec<Option<usize>>,
+    component_stores: ComponentStores,
+    clone_fns: HashMap<TypeId, CloneFn>,
+    resources: HashMap<TypeId, ResourceCell>,
+    resource_clone_fns: HashMap<TypeId, ResourceCloneFn>,
+    non_send_resources: HashMap<TypeId, NonSendCell>,
+    /// The thread which inserted the first non-send resource, the only one
+    /// allowed to run the systems which are not `Send`
+    main_thread: Option<ThreadId>,
+    /// Tick given to the stores and resources modified until the next snapshot
+    change_tick: AtomicU64,
+    panic_policy: PanicPolicy,
+    system_timings: Vec<SystemTiming>,
+    system_panics: Vec<SystemPanic>,
+    system_errors: Vec<SystemError>,
 }
 
 impl Ecs {
@@ -23,7 +79,18 @@ impl Ecs {
         Self {
             next_index: 0,
             deleted_entities_indices: vec![],
-            component_stores: HashMap::new(),
+            generations: vec![],
+            component_stores: ComponentStores::new(),
+            clone_fns: HashMap::new(),
+            resources: HashMap::new(),
+            resource_clone_fns: HashMap::new(),
+            non_send_resources: HashMap::new(),
+            main_thread: None,
+            change_tick: AtomicU64::new(new_change_tick()),
+            panic_policy: PanicPolicy::default(),
+            system_timings: vec![],
+            system_panics: vec![],
+            system_errors: vec![],
         }
     }
 
@@ -32,6 +99,22 @@ impl Ecs {
         self.next_index - self.deleted_entities_indices.len()
     }
 
+    /// Returns whether the entity is still alive
+    #[must_use]
+    pub fn contains(&self, entity_index: EntityIndex) -> bool {
+        self.generations.get(entity_index.index) == Some(&Some(entity_index.generation))
+    }
+
+    /// Iterates over the alive entities in increasing index order
+    pub fn entities(&self) -> impl Iterator<Item = EntityIndex> + '_ {
+        self.generations
+            .iter()
+            .enumerate()
+            .filter_map(|(index, generation)| {
+                generation.map(|generation| EntityIndex { index, generation })
+            })
+    }
+
     pub fn insert<ED>(&mut self, entity_definition: ED) -> EntityIndex
     where
         ED: EntityDefinition,
@@ -41,37 +124,213 @@ impl Ecs {
         entity_index
     }
 
+    /// Creates an empty entity whose components are added one by one
+    ///
+    /// ```
+    /// # use butter_ecs::{Component, Ecs};
+    /// # #[derive(Component)]
+    /// # struct Position(f32, f32);
+    /// # #[derive(Component)]
+    /// # struct Velocity(f32, f32);
+    /// let mut ecs = Ecs::new();
+    /// let entity = ecs
+    ///     .spawn()
+    ///     .with(Position(0.0, 0.0))
+    ///     .with(Velocity(1.0, 0.0))
+    ///     .id();
+    /// assert!(ecs.component::<Velocity>(entity).is_some());
+    /// ```
+    pub fn spawn(&mut self) -> EntityBuilder<'_> {
+        let entity_index = self.allocate_index();
+        EntityBuilder::new(self, entity_index)
+    }
+
+    /// Returns a handle to add or remove components of an existing entity
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the entity has been deleted
+    pub fn entity_mut(&mut self, entity_index: EntityIndex) -> EntityMut<'_> {
+        assert!(
+            self.contains(entity_index),
+            "Entity {} (generation {}) does not exist",
+            entity_index.index,
+            entity_index.generation
+        );
+        EntityMut::new(self, entity_index)
+    }
+
+    /// Inserts all the given entities, reserving the memory they need up front
+    ///
+    /// ```
+    /// # use butter_ecs::{Component, Ecs};
+    /// # #[derive(Component)]
+    /// # struct Bullet;
+    /// # #[derive(Component)]
+    /// # struct Speed(f32);
+    /// let mut ecs = Ecs::new();
+    /// let bullets = ecs.spawn_batch((0..100).map(|i| (Bullet, Speed(i as f32))));
+    /// assert_eq!(bullets.len(), 100);
+    /// ```
+    pub fn spawn_batch<I>(&mut self, entity_definitions: I) -> Vec<EntityIndex>
+    where
+        I: IntoIterator,
+        I::Item: EntityDefinition,
+    {
+        let mut entity_definitions = entity_definitions.into_iter();
+        let (lower_bound, _) = entity_definitions.size_hint();
+        let mut entity_indices = Vec::with_capacity(lower_bound);
+
+        // The first insertion creates the stores the others will need
+        if let Some(entity_definition) = entity_definitions.next() {
+            entity_indices.push(self.insert(entity_definition));
+            self.reserve(lower_bound.saturating_sub(1));
+        }
+
+        entity_indices
+            .extend(entity_definitions.map(|entity_definition| self.insert(entity_definition)));
+        entity_indices
+    }
+
+    /// Reserves memory in every existing store for at least `additional` more entities
+    pub fn reserve(&mut self, additional: usize) {
+        // Deleted indices are reused before allocating new ones
+        let additional_indices = additional.saturating_sub(self.deleted_entities_indices.len());
+        self.generations.reserve(additional_indices);
+
+        let len = (self.next_index + additional_indices).min(BITSET_BIT_COUNT);
+        for store in &mut self.component_stores {
+            store.reserve_exact(len);
+        }
+    }
+
     pub fn delete(&mut self, entity_index: EntityIndex) {
-        for store in self.component_stores.values_mut() {
-            store.remove(entity_index.index);
+        if !self.contains(entity_index) {
+            return;
         }
 
-        self.deleted_entities_indices.push(entity_index);
+        let on_remove_hooks: Vec<_> = self
+            .component_stores
+            .iter()
+            .filter(|store| store.contains(entity_index.index))
+            .map(|store| (store.descriptor.type_id, store.descriptor.on_remove))
+            .collect();
+        for (type_id, on_remove) in on_remove_hooks {
+            // A previous hook may have deleted the entity or the component
+            let still_stored = self.contains(entity_index)
+                && self
+                    .component_stores
+                    .get(&type_id)
+                    .is_some_and(|store| store.contains(entity_index.index));
+            if still_stored {
+                on_remove(self, entity_index);
+            }
+        }
+
+        // A hook may have already deleted the entity
+        if !self.contains(entity_index) {
+            return;
+        }
+
+        let change_tick = self.change_tick();
+        for store in &mut self.component_stores {
+            if store.contains(entity_index.index) {
+                store.remove(entity_index.index);
+                store.mark_changed(change_tick);
+            }
+        }
+        self.release_index(entity_index);
+    }
+
+    /// Releases the memory which is not needed by the components currently
+    /// stored, including the stores left empty
+    pub fn shrink_to_fit(&mut self) {
+        self.component_stores.shrink_to_fit();
+        self.deleted_entities_indices.shrink_to_fit();
+        self.generations.shrink_to_fit();
+    }
+
+    pub fn despawn_batch<I>(&mut self, entity_indices: I)
+    where
+        I: IntoIterator<Item = EntityIndex>,
+    {
+        for entity_index in entity_indices {
+            self.delete(entity_index);
+        }
+    }
+
+    /// Sets what [`Ecs::run_systems`] does when a system panics
+    pub fn set_panic_policy(&mut self, panic_policy: PanicPolicy) {
+        self.panic_policy = panic_policy;
     }
 
+    /// Runs the systems one after the other on the calling thread, which
+    /// must be the main thread if some of them are not
+    /// [`Send`](System::is_send)
+    ///
+    /// # Panics
+    ///
+    /// Will panic with the name of the system if one of them panics and the
+    /// [`PanicPolicy`] is [`Abort`](PanicPolicy::Abort), or before running a
+    /// system which isn't `Send` on another thread than the main one
     pub fn run_systems(&mut self, systems: &mut [Box<dyn System>]) {
         let mut global_command_queue = CommandQueue::new();
         for system in systems.iter_mut() {
-            self.run_system(system, &mut global_command_queue);
+            self.assert_can_run(&**system);
+            let start = Instant::now();
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                system.run((), self);
+            }));
+            self.system_timings.push(SystemTiming {
+                name: system.name(),
+                duration: start.elapsed(),
+            });
+
+            if let Err(payload) = result {
+                let system_panic = SystemPanic::new(system.name(), payload.as_ref());
+                match self.panic_policy {
+                    PanicPolicy::Abort => panic!("{system_panic}"),
+                    PanicPolicy::Continue => {
+                        system.discard_commands();
+                        self.system_panics.push(system_panic);
+                        continue;
+                    }
+                }
+            }
+            global_command_queue.extend(system.command_queue().drain());
         }
 
         self.execute_command_queue(&mut global_command_queue);
     }
 
-    pub fn run_single_system<S>(&mut self, system: &mut S)
+    /// Runs a system and executes its commands, returning its output
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the system isn't [`Send`](System::is_send) and the
+    /// calling thread isn't the main one
+    pub fn run_single_system<S, O>(&mut self, system: &mut S) -> O
     where
-        S: System,
+        S: System<(), O>,
     {
-        system.run(self);
+        self.assert_can_run(system);
+        let output = system.run((), self);
         self.execute_command_queue(system.command_queue());
+        output
     }
 
-    fn run_system<S>(&mut self, system: &mut S, global_command_queue: &mut CommandQueue)
-    where
-        S: System,
-    {
-        system.run(self);
-        global_command_queue.extend(system.command_queue().drain());
+    /// Checks that a system which isn't `Send` runs on the main thread, the
+    /// one which inserted the non-send resources, as the scheduler runs the
+    /// systems on the calling thread
+    fn assert_can_run<O>(&self, system: &(impl System<(), O> + ?Sized)) {
+        assert!(
+            system.is_send()
+                || self
+                    .main_thread
+                    .is_none_or(|main_thread| main_thread == std::thread::current().id()),
+            "System {} isn't Send and can only run on the thread which inserted the non-send resources",
+            system.name()
+        );
     }
 
     fn execute_command_queue(&mut self, command_queue: &mut CommandQueue) {
@@ -81,25 +340,28 @@ impl Ecs {
     }
 
     #[must_use]
-    pub fn component<C: 'static>(&self, entity_index: EntityIndex) -> Option<&C> {
+    pub fn component<C: Component>(&self, entity_index: EntityIndex) -> Option<&C> {
         self.component_at_index(entity_index.index)
     }
 
     #[must_use]
-    pub fn component_mut<C: 'static>(&self, entity_index: EntityIndex) -> Option<&mut C> {
+    #[allow(clippy::mut_from_ref)]
+    pub fn component_mut<C: Component>(&self, entity_index: EntityIndex) -> Option<&mut C> {
         self.component_mut_at_index(entity_index.index)
     }
 
-    fn component_at_index<C: 'static>(&self, index: usize) -> Option<&C> {
+    fn component_at_index<C: Component>(&self, index: usize) -> Option<&C> {
         self.component_stores
             .get(&TypeId::of::<C>())?
             .get::<C>(index)
     }
 
-    fn component_mut_at_index<C: 'static>(&self, index: usize) -> Option<&mut C> {
-        self.component_stores
-            .get(&TypeId::of::<C>())?
-            .get_mut::<C>(index)
+    #[allow(clippy::mut_from_ref)]
+    fn component_mut_at_index<C: Component>(&self, index: usize) -> Option<&mut C> {
+        let store = self.component_stores.get(&TypeId::of::<C>())?;
+        let component = store.get_mut::<C>(index)?;
+        store.mark_changed(self.change_tick());
+        Some(component)
     }
 
     #[must_use]
@@ -110,35 +372,196 @@ impl Ecs {
         query::Iter::new(self)
     }
 
+    /// Lets the components of type `C` be cloned by [`Ecs::clone_entity`]
+    pub fn register_clone<C>(&mut self)
+    where
+        C: Component + Clone,
+    {
+        self.clone_fns
+            .insert(TypeId::of::<C>(), clone_component_fn::<C>);
+    }
+
+    /// Moves an entity and all its components to another `Ecs`, returning its
+    /// index in there
+    ///
+    /// No hook is run since the components are neither created nor destroyed.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the entity has been deleted
+    pub fn transfer(&mut self, entity_index: EntityIndex, other: &mut Ecs) -> EntityIndex {
+        assert!(
+            self.contains(entity_index),
+            "Entity {} (generation {}) does not exist",
+            entity_index.index,
+            entity_index.generation
+        );
+
+        let new_entity_index = other.allocate_index();
+        let change_tick = self.change_tick();
+        let other_change_tick = other.change_tick();
+        for store in &mut self.component_stores {
+            if !store.contains(entity_index.index) {
+                continue;
+            }
+
+            let type_id = store.descriptor.type_id;
+            if let Some(&clone_fn) = self.clone_fns.get(&type_id) {
+                other.clone_fns.entry(type_id).or_insert(clone_fn);
+            }
+
+            let other_store = other
+                .component_stores
+                .get_or_insert_with(type_id, || store.new_empty());
+            // SAFETY:
+            // Both stores hold components of the same type
+            unsafe {
+                let component = store.take(entity_index.index);
+                other_store.store_raw(new_entity_index.index, component);
+            }
+            store.mark_changed(change_tick);
+            other_store.mark_changed(other_change_tick);
+        }
+        self.release_index(entity_index);
+        new_entity_index
+    }
+
+    /// Copies an entity to another `Ecs`, returning its index in there
+    ///
+    /// Only the components registered with [`Ecs::register_clone`] are
+    /// cloned, the other ones are left out.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the entity has been deleted
+    pub fn clone_entity(&self, entity_index: EntityIndex, other: &mut Ecs) -> EntityIndex {
+        assert!(
+            self.contains(entity_index),
+            "Entity {} (generation {}) does not exist",
+            entity_index.index,
+            entity_index.generation
+        );
+
+        let new_entity_index = other.allocate_index();
+        let other_change_tick = other.change_tick();
+        for store in &self.component_stores {
+            let type_id = store.descriptor.type_id;
+            let Some(&clone_fn) = self.clone_fns.get(&type_id) else {
+                continue;
+            };
+            if !store.contains(entity_index.index) {
+                continue;
+            }
+
+            other.clone_fns.entry(type_id).or_insert(clone_fn);
+            let other_store = other
+                .component_stores
+                .get_or_insert_with(type_id, || store.new_empty());
+            // SAFETY:
+            // - Both stores hold components of the same type
+            // - The clone function has been registered for this type
+            unsafe {
+                other_store.store_cloned(
+                    new_entity_index.index,
+                    store.component_ptr(entity_index.index),
+                    clone_fn,
+                );
+            }
+            other_store.mark_changed(other_change_tick);
+        }
+
+        new_entity_index
+    }
+
+    /// Moves every entity of another `Ecs` into this one, which is useful to
+    /// stream in content built on another thread
+    ///
+    /// Returns the new index of each of the moved entities.
+    pub fn merge(&mut self, mut other: Ecs) -> HashMap<EntityIndex, EntityIndex> {
+        let entity_indices: Vec<_> = other.entities().collect();
+        self.reserve(entity_indices.len());
+
+        entity_indices
+            .into_iter()
+            .map(|entity_index| (entity_index, other.transfer(entity_index, self)))
+            .collect()
+    }
+
+    /// Reuses the last deleted index if any, see the determinism section of [`Ecs`]
     fn allocate_index(&mut self) -> EntityIndex {
-        if let Some(reusable_index) = self.deleted_entities_indices.pop() {
+        let entity_index = if let Some(reusable_index) = self.deleted_entities_indices.pop() {
             EntityIndex {
                 index: reusable_index.index,
                 generation: reusable_index.generation + 1,
             }
         } else {
             let next_index = self.next_index;
-            let index = EntityIndex {
+            self.next_index += 1;
+            self.generations.push(None);
+            EntityIndex {
                 index: next_index,
                 generation: 0,
-            };
-            self.next_index += 1;
-            index
-        }
+            }
+        };
+
+        self.generations[entity_index.index] = Some(entity_index.generation);
+        entity_index
+    }
+
+    fn release_index(&mut self, entity_index: EntityIndex) {
+        self.generations[entity_index.index] = None;
+        self.deleted_entities_indices.push(entity_index);
     }
 
     fn store_component<C>(&mut self, index: usize, component: C)
     where
-        C: 'static,
+        C: Component,
     {
+        let change_tick = self.change_tick();
         let component_store = self
             .component_stores
-            .entry(TypeId::of::<C>())
-            .or_insert_with(|| {
-                ComponentStore::new(std::alloc::Layout::new::<C>(), drop_component_fn::<C>)
+            .get_or_insert_with(TypeId::of::<C>(), || {
+                ComponentStore::new(ComponentDescriptor::of::<C>())
             });
 
+        let added = !component_store.contains(index);
         component_store.store(index, component);
+        component_store.mark_changed(change_tick);
+
+        if added {
+            C::on_add(self, self.entity_index_at(index));
+        }
+    }
+
+    fn remove_component<C>(&mut self, index: usize)
+    where
+        C: Component,
+    {
+        let contained = self
+            .component_stores
+            .get(&TypeId::of::<C>())
+            .is_some_and(|component_store| component_store.contains(index));
+        if !contained {
+            return;
+        }
+
+        C::on_remove(self, self.entity_index_at(index));
+        let change_tick = self.change_tick();
+        if let Some(component_store) = self.component_stores.get_mut(&TypeId::of::<C>()) {
+            component_store.remove(index);
+            component_store.mark_changed(change_tick);
+        }
+    }
+
+    fn change_tick(&self) -> u64 {
+        self.change_tick.load(Ordering::Relaxed)
+    }
+
+    fn entity_index_at(&self, index: usize) -> EntityIndex {
+        EntityIndex {
+            index,
+            generation: self.generations[index].expect("The entity is not alive"),
+        }
     }
 }
 
@@ -148,15 +571,69 @@ impl Default for Ecs {
     }
 }
 
+/// Data attached to an entity
+///
+/// Every type stored in the [`Ecs`] must implement it. It is usually derived,
+/// the `component` attribute selecting the storage and the hooks:
+///
+/// ```
+/// # use butter_ecs::{Component, Ecs, EntityIndex};
+/// #[derive(Component)]
+/// #[component(storage = "dense", on_add = spawned)]
+/// struct Enemy;
+///
+/// fn spawned(_ecs: &mut Ecs, _entity_index: EntityIndex) {
+///     println!("An enemy appeared!");
+/// }
+/// ```
+pub trait Component: Send + Sync + 'static {
+    const STORAGE: StorageType = StorageType::Dense;
+
+    /// Called after the component has been added to an entity which did not
+    /// have one yet
+    fn on_add(_ecs: &mut Ecs, _entity_index: EntityIndex) {}
+
+    /// Called before the component is removed from an entity, either
+    /// explicitly or because the entity is deleted
+    fn on_remove(_ecs: &mut Ecs, _entity_index: EntityIndex) {}
+}
+
+/// How the components of a given type are stored
+///
+/// Queries can mix components of both kinds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageType {
+    /// One slot per entity index, making lookups and iteration fast
+    Dense,
+    /// Components packed in a dense array indexed through a sparse one, making
+    /// adding and removing them cheap. Best suited to components that are
+    /// frequently added and removed, like markers.
+    SparseSet,
+}
+
+/// A set of components inserted together
+///
+/// It is implemented for every [`Component`], for tuples of entity
+/// definitions (which can therefore be nested) and can be derived for structs
+/// whose fields are all entity definitions.
 pub trait EntityDefinition {
     fn store_component(self, ecs: &mut Ecs, index: usize);
 }
 
+impl<C> EntityDefinition for C
+where
+    C: Component,
+{
+    fn store_component(self, ecs: &mut Ecs, index: usize) {
+        ecs.store_component(index, self);
+    }
+}
+
 macro_rules! impl_entity_definition_for_tuple {
     ($($t:tt: $i:tt,)*) => {
-        impl<$($t: 'static,)*> EntityDefinition for ($($t,)*) {
+        impl<$($t: EntityDefinition,)*> EntityDefinition for ($($t,)*) {
             fn store_component(self, ecs: &mut Ecs, index: usize) {
-                $(ecs.store_component(index, self.$i);)*
+                $(self.$i.store_component(ecs, index);)*
             }
         }
     }
@@ -177,78 +654,273 @@ impl_entity_definition_for_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7
 impl_entity_definition_for_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10, L: 11, M: 12,);
 impl_entity_definition_for_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10, L: 11, M: 12, N: 13,);
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct EntityIndex {
     index: usize,
     generation: usize,
 }
 
 const BITSET_BIT_COUNT: usize = 65536;
+const MIN_CAPACITY: usize = 8;
 const fn bitset_word_count<T>() -> usize {
     BITSET_BIT_COUNT / (std::mem::size_of::<T>() * 8)
 }
 
+/// What a store needs to know about the type of its components
+#[derive(Clone, Copy)]
+struct ComponentDescriptor {
+    type_id: TypeId,
+    name: &'static str,
+    layout: Layout,
+    drop: unsafe fn(*mut u8),
+    on_remove: fn(&mut Ecs, EntityIndex),
+    storage: StorageType,
+}
+
+impl ComponentDescriptor {
+    fn of<C: Component>() -> Self {
+        Self {
+            type_id: TypeId::of::<C>(),
+            name: std::any::type_name::<C>(),
+            layout: Layout::new::<C>(),
+            drop: drop_component_fn::<C>,
+            on_remove: C::on_remove,
+            storage: C::STORAGE,
+        }
+    }
+}
+
+/// Components are stored in slots, which are:
+/// - the entity indices themselves for dense stores
+/// - packed at the beginning of the data for sparse sets, so that adding and
+///   removing components doesn't leave holes
 struct ComponentStore {
     data: NonNull<u8>,
-    layout: Layout,
+    descriptor: ComponentDescriptor,
+    /// Number of slots in use: one past the highest index holding a component
+    /// for dense stores, the number of components for sparse sets
     len: usize,
-    reserved_len: usize,
-    drop: unsafe fn(*mut u8),
+    /// Number of components actually stored
+    count: usize,
+    /// Number of slots the data has room for, `usize::MAX` for zero-sized
+    /// components which don't need any memory
+    capacity: usize,
     entities_bitset: [u64; bitset_word_count::<u64>()],
+    /// Sparse sets only: slot of the component of each entity index
+    sparse: Vec<usize>,
+    /// Sparse sets only: entity index of the component in each slot
+    dense_entities: Vec<usize>,
+    /// Change tick of the last modification, see [`Ecs::snapshot`]
+    last_changed: AtomicU64,
 }
 
 impl ComponentStore {
-    pub fn new(component_layout: Layout, drop: unsafe fn(*mut u8)) -> Self {
-        let reserved_len = if component_layout.size() == 0 {
+    pub fn new(descriptor: ComponentDescriptor) -> Self {
+        let capacity = if descriptor.layout.size() == 0 {
             usize::MAX
         } else {
             0
         };
 
         Self {
-            data: NonNull::dangling(),
-            layout: component_layout,
+            data: dangling(descriptor.layout),
+            descriptor,
             len: 0,
-            reserved_len,
-            drop,
+            count: 0,
+            capacity,
             entities_bitset: [0u64; bitset_word_count::<u64>()],
+            sparse: vec![],
+            dense_entities: vec![],
+            last_changed: AtomicU64::new(0),
         }
     }
 
-    pub fn store<C>(&mut self, index: usize, mut component: C) {
-        assert!(index < BITSET_BIT_COUNT, "ComponentStore is full");
-        self.entities_bitset.set_bit(index);
-        self.resize(index + 1);
+    /// Creates a store for the same type of components
+    pub fn new_empty(&self) -> Self {
+        Self::new(self.descriptor)
+    }
 
-        if self.layout.size() > 0 {
+    pub fn store<C>(&mut self, index: usize, component: C) {
+        let mut component = std::mem::ManuallyDrop::new(component);
+        // SAFETY:
+        // The component is owned by the store from now on
+        unsafe { self.store_raw(index, std::ptr::addr_of_mut!(component).cast()) };
+    }
+
+    /// # Safety
+    /// `component` must point to a component of the type held by this store,
+    /// which is moved into the store
+    pub unsafe fn store_raw(&mut self, index: usize, component: *mut u8) {
+        let slot = self.prepare_slot(index);
+
+        if self.descriptor.layout.size() > 0 {
             // SAFETY:
             // The chunked of data has just been resized to ensure it can store the component
-            unsafe { self.write(index, std::ptr::addr_of_mut!(component).cast()) };
+            self.write(slot, component);
         }
     }
 
-    pub fn get<C>(&self, index: usize) -> Option<&C> {
-        if index >= self.len {
-            return None;
+    /// # Safety
+    /// - `component` must point to a component of the type held by this store
+    /// - `clone` must be the clone function of this type
+    pub unsafe fn store_cloned(&mut self, index: usize, component: *const u8, clone: CloneFn) {
+        let slot = self.prepare_slot(index);
+        clone(component, self.ptr_at(slot));
+    }
+
+    /// Copies the store, cloning each of its components
+    ///
+    /// # Safety
+    /// `clone` must be the clone function of the type held by this store
+    unsafe fn clone_with(&self, clone: CloneFn) -> Self {
+        let mut store = self.new_empty();
+        store.reserve_exact(self.len);
+
+        // Sparse sets are cloned in slot order so that they stay identical
+        let indices: Vec<usize> = match self.descriptor.storage {
+            StorageType::Dense => (0..self.len).filter(|&i| self.contains(i)).collect(),
+            StorageType::SparseSet => self.dense_entities.clone(),
+        };
+        for index in indices {
+            store.store_cloned(index, self.component_ptr(index), clone);
         }
+        store
+    }
 
-        if !self.entities_bitset.bit(index) {
-            return None;
+    fn last_changed(&self) -> u64 {
+        self.last_changed.load(Ordering::Relaxed)
+    }
+
+    fn mark_changed(&self, change_tick: u64) {
+        self.last_changed.store(change_tick, Ordering::Relaxed);
+    }
+
+    /// Memory used by the store, including its bookkeeping
+    fn memory(&self) -> usize {
+        let data = if self.descriptor.layout.size() == 0 {
+            0
+        } else {
+            self.capacity * self.descriptor.layout.size()
+        };
+
+        data + std::mem::size_of_val(&self.entities_bitset)
+            + (self.sparse.capacity() + self.dense_entities.capacity())
+                * std::mem::size_of::<usize>()
+    }
+
+    /// Returns the slot in which the component of the entity can be written
+    fn prepare_slot(&mut self, index: usize) -> usize {
+        assert!(index < BITSET_BIT_COUNT, "ComponentStore is full");
+        self.remove(index);
+        self.entities_bitset.set_bit(index);
+        self.count += 1;
+
+        match self.descriptor.storage {
+            StorageType::Dense => {
+                self.resize(index + 1);
+                index
+            }
+            StorageType::SparseSet => {
+                let slot = self.len;
+                self.resize(slot + 1);
+                if self.sparse.len() <= index {
+                    self.sparse.resize(index + 1, usize::MAX);
+                }
+                self.sparse[index] = slot;
+                self.dense_entities.push(index);
+                slot
+            }
+        }
+    }
+
+    /// Removes the component without dropping it
+    ///
+    /// # Safety
+    /// - The store must contain a component at this index
+    /// - The returned pointer is only valid until the store is modified
+    pub unsafe fn take(&mut self, index: usize) -> *mut u8 {
+        let slot = self.slot(index).expect("No component at this index");
+        self.entities_bitset.unset_bit(index);
+        self.count -= 1;
+
+        match self.descriptor.storage {
+            StorageType::Dense => {
+                let ptr = self.ptr_at(slot);
+                // The slots after the last component are not in use anymore
+                while self.len > 0 && !self.entities_bitset.bit(self.len - 1) {
+                    self.len -= 1;
+                }
+                ptr
+            }
+            StorageType::SparseSet => {
+                // The taken component is swapped with the last one, then
+                // left right after the packed components
+                let last_slot = self.len - 1;
+                if slot != last_slot {
+                    std::ptr::swap_nonoverlapping(
+                        self.ptr_at(slot),
+                        self.ptr_at(last_slot),
+                        self.descriptor.layout.size(),
+                    );
+                    let moved_index = self.dense_entities[last_slot];
+                    self.sparse[moved_index] = slot;
+                }
+                self.dense_entities.swap_remove(slot);
+                self.len -= 1;
+                self.ptr().add(last_slot * self.descriptor.layout.size())
+            }
         }
+    }
 
-        unsafe { Some(&*self.ptr_at(index).cast::<C>()) }
+    pub fn contains(&self, index: usize) -> bool {
+        index < BITSET_BIT_COUNT && self.entities_bitset.bit(index)
     }
 
-    pub fn get_mut<C>(&self, index: usize) -> Option<&mut C> {
-        if index >= self.len {
+    fn slot(&self, index: usize) -> Option<usize> {
+        if !self.contains(index) {
             return None;
         }
 
-        if !self.entities_bitset.bit(index) {
-            return None;
+        match self.descriptor.storage {
+            StorageType::Dense => Some(index),
+            StorageType::SparseSet => Some(self.sparse[index]),
         }
+    }
+
+    pub fn get<C>(&self, index: usize) -> Option<&C> {
+        let slot = self.slot(index)?;
+        unsafe { Some(&*self.ptr_at(slot).cast::<C>()) }
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    pub fn get_mut<C>(&self, index: usize) -> Option<&mut C> {
+        let slot = self.slot(index)?;
+        unsafe { Some(&mut *self.ptr_at(slot).cast::<C>()) }
+    }
+
+    /// # Safety
+    /// - The store must contain a component at each of the indices
+    /// - The store must be a dense one, whose slots are the indices
+    pub unsafe fn slice<C>(&self, indices: Range<usize>) -> &[C] {
+        debug_assert!(self.descriptor.storage == StorageType::Dense && indices.end <= self.len);
+        std::slice::from_raw_parts(self.ptr().cast::<C>().add(indices.start), indices.len())
+    }
+
+    /// # Safety
+    /// See `ComponentStore::slice`
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn slice_mut<C>(&self, indices: Range<usize>) -> &mut [C] {
+        debug_assert!(self.descriptor.storage == StorageType::Dense && indices.end <= self.len);
+        std::slice::from_raw_parts_mut(self.ptr().cast::<C>().add(indices.start), indices.len())
+    }
 
-        unsafe { Some(&mut *self.ptr_at(index).cast::<C>()) }
+    /// # Panics
+    /// Will panic if the store doesn't contain a component at this index
+    pub fn component_ptr(&self, index: usize) -> *mut u8 {
+        let slot = self.slot(index).expect("No component at this index");
+        // SAFETY:
+        // The slot of a stored component is in bounds
+        unsafe { self.ptr_at(slot) }
     }
 
     pub fn ptr(&self) -> *mut u8 {
@@ -256,83 +928,126 @@ impl ComponentStore {
     }
 
     /// # Safety
-    /// The caller must ensures that index is < self.len
-    pub unsafe fn ptr_at(&self, index: usize) -> *mut u8 {
-        assert!(index < self.len);
-        self.ptr().add(index * self.layout.size())
+    /// The caller must ensures that slot is < self.len
+    pub unsafe fn ptr_at(&self, slot: usize) -> *mut u8 {
+        assert!(slot < self.len);
+        self.ptr().add(slot * self.descriptor.layout.size())
     }
 
     pub fn remove(&mut self, index: usize) {
-        if !self.entities_bitset.bit(index) || index >= self.len {
+        if !self.contains(index) {
             return;
         }
 
-        self.entities_bitset.unset_bit(index);
-
         // SAFETY:
-        // index is inside the bounds of the allocated memory
+        // - The store contains a component at this index
+        // - The component is dropped before any other modification
         unsafe {
-            let ptr = self.ptr_at(index);
-            (self.drop)(ptr);
+            let ptr = self.take(index);
+            (self.descriptor.drop)(ptr);
         }
-        self.len -= 1;
     }
 
     pub fn clear(&mut self) {
-        let len = self.len;
-        for i in 0..len {
-            self.remove(i);
+        match self.descriptor.storage {
+            StorageType::Dense => {
+                for i in (0..self.len).rev() {
+                    self.remove(i);
+                }
+            }
+            StorageType::SparseSet => {
+                while let Some(&index) = self.dense_entities.last() {
+                    self.remove(index);
+                }
+            }
         }
     }
 
     fn resize(&mut self, len: usize) {
-        self.reserve_exact(len);
-        self.len = len;
+        self.reserve(len);
+        self.len = self.len.max(len);
+    }
+
+    /// Grows the store geometrically so that consecutive insertions only
+    /// reallocate a logarithmic number of times
+    fn reserve(&mut self, len: usize) {
+        if self.capacity >= len {
+            return;
+        }
+
+        let new_capacity = len
+            .max(self.capacity * 2)
+            .max(MIN_CAPACITY)
+            .min(BITSET_BIT_COUNT.max(len));
+        self.set_capacity(new_capacity);
     }
 
     fn reserve_exact(&mut self, len: usize) {
-        if self.reserved_len >= len {
+        if self.capacity >= len {
             return;
         }
 
-        let new_reserved_len = len;
-        let new_layout = array_layout(self.layout, new_reserved_len);
+        self.set_capacity(len);
+    }
+
+    /// Releases the memory of the slots which are not in use
+    fn shrink_to_fit(&mut self) {
+        if self.descriptor.layout.size() > 0 && self.capacity > self.len {
+            self.set_capacity(self.len);
+        }
+
+        if self.descriptor.storage == StorageType::SparseSet {
+            let sparse_len = self.dense_entities.iter().max().map_or(0, |&i| i + 1);
+            self.sparse.truncate(sparse_len);
+            self.sparse.shrink_to_fit();
+            self.dense_entities.shrink_to_fit();
+        }
+    }
+
+    /// Reallocates the data, the new capacity must not be less than `len`
+    fn set_capacity(&mut self, new_capacity: usize) {
+        debug_assert!(self.descriptor.layout.size() > 0 && new_capacity >= self.len);
+        let old_layout = array_layout(self.descriptor.layout, self.capacity);
+        let new_layout = array_layout(self.descriptor.layout, new_capacity);
 
         // SAFETY:
-        // - The layout is guaranteed to have a non-zero size because we don't reserve
-        //   when using ZST
+        // - The layouts have a non-zero size unless the capacity is zero,
+        //   since zero-sized components never get reallocated
+        // - The data has been allocated with the old layout unless the
+        //   capacity was zero
         let new_data = unsafe {
-            if self.reserved_len == 0 {
+            if new_capacity == 0 {
+                std::alloc::dealloc(self.data.as_ptr(), old_layout);
+                dangling(self.descriptor.layout).as_ptr()
+            } else if self.capacity == 0 {
                 std::alloc::alloc(new_layout)
             } else {
-                std::alloc::realloc(
-                    self.data.as_ptr(),
-                    array_layout(self.layout, self.reserved_len),
-                    new_layout.size(),
-                )
+                std::alloc::realloc(self.data.as_ptr(), old_layout, new_layout.size())
             }
         };
 
-        self.reserved_len = new_reserved_len;
+        self.capacity = new_capacity;
         self.data = NonNull::new(new_data).expect("ComponentStore allocation failed");
     }
 
     /// # Safety
-    /// - index must be in the bounds of the allocated chunk of data
-    unsafe fn write(&mut self, index: usize, data_ptr: *mut u8) {
-        let dst_ptr = self.ptr_at(index);
-        std::ptr::copy_nonoverlapping(data_ptr, dst_ptr, self.layout.size());
+    /// - slot must be in the bounds of the allocated chunk of data
+    unsafe fn write(&mut self, slot: usize, data_ptr: *mut u8) {
+        let dst_ptr = self.ptr_at(slot);
+        std::ptr::copy_nonoverlapping(data_ptr, dst_ptr, self.descriptor.layout.size());
     }
 }
 
 impl Drop for ComponentStore {
     fn drop(&mut self) {
-        if self.layout.size() == 0 {
+        // Zero-sized components still need to be dropped
+        self.clear();
+
+        if self.descriptor.layout.size() == 0 || self.capacity == 0 {
             return;
         }
 
-        self.clear();
-        let layout = array_layout(self.layout, self.reserved_len);
+        let layout = array_layout(self.descriptor.layout, self.capacity);
 
         // SAFETY:
         // - self.data has been allocated with the same allocator
@@ -344,10 +1059,33 @@ impl Drop for ComponentStore {
     }
 }
 
+// SAFETY:
+// Components are required to be `Send` and `Sync`
+unsafe impl Send for ComponentStore {}
+unsafe impl Sync for ComponentStore {}
+
 unsafe fn drop_component_fn<T>(ptr: *mut u8) {
     ptr.cast::<T>().drop_in_place();
 }
 
+/// Change ticks are unique across all the `Ecs` so that a snapshot can't
+/// be mistaken for the state of another world
+fn new_change_tick() -> u64 {
+    static NEXT_CHANGE_TICK: AtomicU64 = AtomicU64::new(1);
+    NEXT_CHANGE_TICK.fetch_add(1, Ordering::Relaxed)
+}
+
+type CloneFn = unsafe fn(*const u8, *mut u8);
+
+unsafe fn clone_component_fn<T: Clone>(src: *const u8, dst: *mut u8) {
+    dst.cast::<T>().write((*src.cast::<T>()).clone());
+}
+
+/// A well aligned pointer for stores which haven't allocated anything
+fn dangling(layout: Layout) -> NonNull<u8> {
+    NonNull::new(std::ptr::without_provenance_mut(layout.align())).expect("Alignments are non-zero")
+}
+
 // TODO: Remove when std::alloc::Layout::array stabilizes
 const fn array_layout(layout: Layout, len: usize) -> Layout {
     let array_size = layout.size() * len;
@@ -367,6 +1105,10 @@ const fn max_size_for_align(align: usize) -> usize {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    };
 
     #[test]
     fn ecs_new() {
@@ -374,13 +1116,13 @@ mod tests {
         assert_eq!(ecs.entity_count(), 0);
     }
 
-    #[derive(Debug, Eq, PartialEq)]
+    #[derive(Clone, Debug, Eq, PartialEq, Component)]
     struct Player;
-    #[derive(Debug, Eq, PartialEq)]
+    #[derive(Debug, Eq, PartialEq, Component)]
     struct Enemy;
-    #[derive(Debug, Eq, PartialEq)]
+    #[derive(Debug, Eq, PartialEq, Component)]
     struct Health(i16);
-    #[derive(Debug, Eq, PartialEq)]
+    #[derive(Debug, Eq, PartialEq, Component)]
     struct Level(u16);
 
     #[test]
@@ -429,6 +1171,77 @@ mod tests {
         assert_eq!(player.generation, 1);
     }
 
+    #[test]
+    fn ecs_reuse_indices_last_in_first_out() {
+        let mut ecs = Ecs::new();
+        let entities: Vec<_> = (0..5).map(|i| ecs.insert((Health(i),))).collect();
+
+        ecs.delete(entities[3]);
+        ecs.delete(entities[1]);
+
+        assert_eq!(ecs.insert((Health(10),)).index, 1);
+        assert_eq!(ecs.insert((Health(11),)).index, 3);
+        assert_eq!(ecs.insert((Health(12),)).index, 5);
+    }
+
+    type DropLog = std::sync::Arc<std::sync::Mutex<Vec<&'static str>>>;
+
+    macro_rules! logged_component {
+        ($name:ident, $label:literal) => {
+            #[derive(Component)]
+            struct $name(DropLog);
+
+            impl Drop for $name {
+                fn drop(&mut self) {
+                    self.0.lock().unwrap().push($label);
+                }
+            }
+        };
+    }
+
+    logged_component!(Sword, "sword");
+    logged_component!(Armor, "armor");
+    logged_component!(Shield, "shield");
+
+    fn replay_script() -> (Vec<EntityIndex>, Vec<EntityIndex>, Vec<&'static str>) {
+        let log = DropLog::default();
+        let mut ecs = Ecs::new();
+        let mut spawned = vec![];
+
+        for i in 0..30 {
+            let entity = match i % 3 {
+                0 => ecs.insert((Sword(log.clone()), Armor(log.clone()))),
+                1 => ecs.insert((Armor(log.clone()), Shield(log.clone()))),
+                _ => ecs.insert((Shield(log.clone()), Sword(log.clone()), Armor(log.clone()))),
+            };
+            spawned.push(entity);
+            if i % 4 == 3 {
+                ecs.delete(spawned[i - 2]);
+            }
+        }
+        for &entity in spawned.iter().rev().step_by(3) {
+            ecs.delete(entity);
+        }
+        for _ in 0..5 {
+            spawned.push(ecs.insert((Shield(log.clone()),)));
+        }
+
+        let alive = ecs.entities().collect();
+        let drops = log.lock().unwrap().clone();
+        (spawned, alive, drops)
+    }
+
+    #[test]
+    fn ecs_deterministic_replay() {
+        let first_run = replay_script();
+        let second_run = replay_script();
+        assert_eq!(first_run, second_run);
+
+        // Components are dropped in the order their type was first stored
+        let (_, _, drops) = first_run;
+        assert_eq!(drops[..5], ["armor", "shield", "sword", "armor", "shield"]);
+    }
+
     #[test]
     fn ecs_component() {
         let mut ecs = Ecs::new();
@@ -489,4 +1302,464 @@ mod tests {
         assert_eq!(health_iter.next(), Some(&Health(0)));
         assert_eq!(health_iter.next(), None);
     }
+
+    #[test]
+    fn ecs_delete_twice() {
+        let mut ecs = Ecs::new();
+        let player = ecs.insert((Player, Health(10)));
+        ecs.delete(player);
+        ecs.delete(player);
+        assert!(!ecs.contains(player));
+        assert_eq!(ecs.entity_count(), 0);
+
+        let first = ecs.insert((Player,));
+        let second = ecs.insert((Player,));
+        assert_ne!(first.index, second.index);
+    }
+
+    #[test]
+    fn ecs_insert_nested_entity_definitions() {
+        #[derive(EntityDefinition)]
+        struct Character {
+            level: Level,
+            health: Health,
+        }
+
+        let mut ecs = Ecs::new();
+        let player = ecs.insert((
+            Player,
+            Character {
+                level: Level(3),
+                health: Health(10),
+            },
+        ));
+        assert_eq!(ecs.component::<Player>(player), Some(&Player));
+        assert_eq!(ecs.component::<Level>(player), Some(&Level(3)));
+        assert_eq!(ecs.component::<Health>(player), Some(&Health(10)));
+    }
+
+    #[test]
+    fn ecs_insert_bundle() {
+        #[derive(Bundle)]
+        struct EnemyBundle(Enemy, Health);
+
+        let mut ecs = Ecs::new();
+        let enemy = ecs.insert((EnemyBundle(Enemy, Health(5)), Level(2)));
+        assert_eq!(ecs.component::<Enemy>(enemy), Some(&Enemy));
+        assert_eq!(ecs.component::<Health>(enemy), Some(&Health(5)));
+        assert_eq!(ecs.component::<Level>(enemy), Some(&Level(2)));
+    }
+
+    #[derive(Component)]
+    #[component(on_add = level_up, on_remove = level_down)]
+    struct Veteran;
+
+    fn level_up(ecs: &mut Ecs, entity_index: EntityIndex) {
+        ecs.entity_mut(entity_index).insert(Level(1));
+    }
+
+    fn level_down(ecs: &mut Ecs, entity_index: EntityIndex) {
+        ecs.entity_mut(entity_index).remove::<Level>();
+    }
+
+    #[test]
+    fn ecs_component_hooks() {
+        let mut ecs = Ecs::new();
+        let player = ecs.insert((Player, Veteran));
+        assert_eq!(ecs.component::<Level>(player), Some(&Level(1)));
+
+        ecs.component_mut::<Level>(player).unwrap().0 = 2;
+        ecs.entity_mut(player).insert(Veteran);
+        assert_eq!(ecs.component::<Level>(player), Some(&Level(2)));
+
+        ecs.entity_mut(player).remove::<Veteran>();
+        assert_eq!(ecs.component::<Level>(player), None);
+
+        let enemy = ecs.insert((Enemy, Veteran));
+        assert_eq!(ecs.query::<&Level>().count(), 1);
+        ecs.delete(enemy);
+        assert_eq!(ecs.query::<&Level>().count(), 0);
+    }
+
+    #[derive(Component)]
+    #[component(on_remove = remove_counted)]
+    struct Owner;
+
+    #[derive(Component)]
+    #[component(on_remove = count_removal)]
+    struct Counted(Arc<AtomicU8>);
+
+    fn remove_counted(ecs: &mut Ecs, entity_index: EntityIndex) {
+        ecs.entity_mut(entity_index).remove::<Counted>();
+    }
+
+    fn count_removal(ecs: &mut Ecs, entity_index: EntityIndex) {
+        let removals = &ecs.component::<Counted>(entity_index).unwrap().0;
+        removals.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[test]
+    fn ecs_delete_with_nested_removal() {
+        let removals = Arc::new(AtomicU8::new(0));
+        let mut ecs = Ecs::new();
+        let player = ecs.insert((Owner, Counted(removals.clone())));
+        ecs.delete(player);
+        assert_eq!(removals.load(Ordering::Relaxed), 1);
+        assert!(!ecs.contains(player));
+    }
+
+    #[test]
+    fn ecs_spawn_batch() {
+        let mut ecs = Ecs::new();
+        let enemies = ecs.spawn_batch((0..100).map(|i| (Enemy, Health(i))));
+        assert_eq!(enemies.len(), 100);
+        assert_eq!(ecs.entity_count(), 100);
+        assert_eq!(ecs.component::<Health>(enemies[42]), Some(&Health(42)));
+        assert!(ecs.query::<&Health>().map(|health| health.0).eq(0..100));
+    }
+
+    #[test]
+    fn ecs_despawn_batch() {
+        let mut ecs = Ecs::new();
+        let enemies = ecs.spawn_batch((0..10).map(|i| (Enemy, Health(i))));
+        let (despawned, alive): (Vec<_>, Vec<_>) =
+            enemies.into_iter().partition(|enemy| enemy.index % 2 == 0);
+        ecs.despawn_batch(despawned.iter().copied());
+
+        assert_eq!(ecs.entity_count(), 5);
+        assert!(despawned.iter().all(|&enemy| !ecs.contains(enemy)));
+        assert!(despawned
+            .iter()
+            .all(|&enemy| ecs.component::<Health>(enemy).is_none()));
+        assert!(alive.iter().all(|&enemy| ecs.contains(enemy)));
+    }
+
+    #[test]
+    fn ecs_reserve() {
+        let mut ecs = Ecs::new();
+        ecs.insert((Player, Health(10)));
+        ecs.reserve(1000);
+        let health_store = ecs.component_stores.get(&TypeId::of::<Health>()).unwrap();
+        assert!(health_store.capacity >= 1001);
+    }
+
+    #[test]
+    fn component_store_grows_geometrically() {
+        let mut store = ComponentStore::new(ComponentDescriptor::of::<Health>());
+        let mut reallocations = 0;
+        for i in 0..1000 {
+            let capacity = store.capacity;
+            store.store(i, Health(0));
+            if store.capacity != capacity {
+                reallocations += 1;
+            }
+        }
+        assert!(reallocations <= 8);
+    }
+
+    #[derive(Clone, Debug, Eq, PartialEq, Component)]
+    #[component(storage = "sparse_set")]
+    struct Nickname(String);
+
+    #[test]
+    fn component_store_len_follows_last_component() {
+        let mut store = ComponentStore::new(ComponentDescriptor::of::<Name>());
+        store.store(2, Name("first".to_string()));
+        store.store(5, Name("second".to_string()));
+        assert_eq!(store.len, 6);
+
+        store.remove(2);
+        assert_eq!(store.len, 6);
+        store.remove(5);
+        assert_eq!(store.len, 0);
+        assert_eq!(store.count, 0);
+    }
+
+    #[test]
+    fn ecs_delete_keeps_later_components_reachable() {
+        let mut ecs = Ecs::new();
+        let first = ecs.insert((Health(1),));
+        let second = ecs.insert((Health(2),));
+        let third = ecs.insert((Health(3),));
+
+        ecs.delete(second);
+        ecs.delete(first);
+        assert_eq!(ecs.component::<Health>(third), Some(&Health(3)));
+        assert!(ecs.query::<&Health>().eq([&Health(3)]));
+    }
+
+    #[test]
+    fn component_store_drops_components_once() {
+        static DROP_COUNT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+        #[derive(Component)]
+        struct Tracked(#[allow(dead_code)] u64);
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let mut store = ComponentStore::new(ComponentDescriptor::of::<Tracked>());
+        store.store(0, Tracked(1));
+        store.store(1, Tracked(2));
+        assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 0);
+
+        store.store(0, Tracked(3));
+        assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 1);
+        store.remove(1);
+        assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 2);
+        drop(store);
+        assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 3);
+    }
+
+    // Heap allocated components let Miri catch leaks, double drops and
+    // accesses to moved slots
+    #[test]
+    fn component_store_insert_remove_interleavings() {
+        let mut dense = ComponentStore::new(ComponentDescriptor::of::<Name>());
+        let mut sparse = ComponentStore::new(ComponentDescriptor::of::<Nickname>());
+        for i in 0..40 {
+            dense.store(i % 13, Name(i.to_string()));
+            sparse.store(i % 11, Nickname(i.to_string()));
+            if i % 3 == 0 {
+                dense.remove((i * 7) % 13);
+                sparse.remove((i * 5) % 11);
+            }
+            if i % 10 == 9 {
+                dense.shrink_to_fit();
+                sparse.shrink_to_fit();
+            }
+        }
+
+        for index in 0..13 {
+            if let Some(name) = dense.get::<Name>(index) {
+                assert_eq!(name.0.parse::<usize>().unwrap() % 13, index);
+            }
+        }
+        for index in 0..11 {
+            if let Some(nickname) = sparse.get::<Nickname>(index) {
+                assert_eq!(nickname.0.parse::<usize>().unwrap() % 11, index);
+            }
+        }
+        assert_eq!(sparse.len, sparse.count);
+    }
+
+    #[test]
+    fn ecs_shrink_to_fit() {
+        let mut ecs = Ecs::new();
+        let entities =
+            ecs.spawn_batch((0..100).map(|i| (Name(i.to_string()), Nickname(i.to_string()))));
+        ecs.despawn_batch(entities[10..].iter().copied());
+        ecs.shrink_to_fit();
+
+        let store = ecs.component_stores.get(&TypeId::of::<Name>()).unwrap();
+        assert_eq!(store.capacity, 10);
+        let store = ecs.component_stores.get(&TypeId::of::<Nickname>()).unwrap();
+        assert_eq!(store.capacity, 10);
+        for (i, &entity) in entities[..10].iter().enumerate() {
+            assert_eq!(ecs.component::<Name>(entity), Some(&Name(i.to_string())));
+            assert_eq!(
+                ecs.component::<Nickname>(entity),
+                Some(&Nickname(i.to_string()))
+            );
+        }
+
+        ecs.despawn_batch(entities[..10].iter().copied());
+        ecs.shrink_to_fit();
+        assert!(ecs.component_infos().is_empty());
+    }
+
+    #[test]
+    fn ecs_removes_empty_stores() {
+        let mut ecs = Ecs::new();
+        let player = ecs.insert((Player, Health(10)));
+        let enemy = ecs.insert((Enemy, Health(5)));
+
+        ecs.entity_mut(player).remove::<Player>();
+        ecs.delete(enemy);
+        assert!(ecs.component_stores.get(&TypeId::of::<Player>()).is_some());
+
+        ecs.shrink_to_fit();
+        assert!(ecs.component_stores.get(&TypeId::of::<Player>()).is_none());
+        assert!(ecs.component_stores.get(&TypeId::of::<Enemy>()).is_none());
+        assert!(ecs.component_stores.get(&TypeId::of::<Health>()).is_some());
+
+        ecs.entity_mut(player).insert(Player);
+        assert_eq!(ecs.component::<Player>(player), Some(&Player));
+        assert_eq!(ecs.component::<Health>(player), Some(&Health(10)));
+    }
+
+    #[test]
+    fn ecs_drops_zero_sized_components() {
+        static DROP_COUNT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+        #[derive(Component)]
+        struct Marker;
+        impl Drop for Marker {
+            fn drop(&mut self) {
+                DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let mut ecs = Ecs::new();
+        let first = ecs.insert((Marker, Health(1)));
+        ecs.insert((Marker, Health(2)));
+        ecs.insert((Marker, Health(3)));
+        assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 0);
+
+        ecs.delete(first);
+        assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 1);
+
+        drop(ecs);
+        assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 3);
+    }
+
+    #[derive(Clone, Debug, Eq, PartialEq, Component)]
+    struct Name(String);
+
+    #[test]
+    fn ecs_entities() {
+        let mut ecs = Ecs::new();
+        let player = ecs.insert((Player,));
+        let enemy = ecs.insert((Enemy,));
+        let other_enemy = ecs.insert((Enemy,));
+        ecs.delete(enemy);
+        assert!(ecs.entities().eq([player, other_enemy]));
+    }
+
+    #[test]
+    fn ecs_transfer() {
+        let mut simulation = Ecs::new();
+        let mut preview = Ecs::new();
+        preview.insert((Enemy,));
+
+        let player = simulation.insert((Player, Name("John".into()), Health(10)));
+        let moved_player = simulation.transfer(player, &mut preview);
+
+        assert!(!simulation.contains(player));
+        assert_eq!(simulation.entity_count(), 0);
+        assert_eq!(simulation.query::<&Name>().count(), 0);
+
+        assert_eq!(moved_player.index, 1);
+        assert_eq!(preview.entity_count(), 2);
+        assert_eq!(preview.component::<Player>(moved_player), Some(&Player));
+        assert_eq!(
+            preview.component::<Name>(moved_player),
+            Some(&Name("John".into()))
+        );
+        assert_eq!(preview.component::<Health>(moved_player), Some(&Health(10)));
+    }
+
+    #[test]
+    fn ecs_clone_entity() {
+        let mut simulation = Ecs::new();
+        let mut preview = Ecs::new();
+        simulation.register_clone::<Name>();
+        simulation.register_clone::<Player>();
+
+        let player = simulation.insert((Player, Name("John".into()), Health(10)));
+        let cloned_player = simulation.clone_entity(player, &mut preview);
+
+        assert_eq!(
+            simulation.component::<Name>(player),
+            Some(&Name("John".into()))
+        );
+        assert_eq!(preview.component::<Player>(cloned_player), Some(&Player));
+        assert_eq!(
+            preview.component::<Name>(cloned_player),
+            Some(&Name("John".into()))
+        );
+        assert_eq!(preview.component::<Health>(cloned_player), None);
+
+        // The clone registration follows the components
+        let mut other_preview = Ecs::new();
+        let cloned_twice = preview.clone_entity(cloned_player, &mut other_preview);
+        assert_eq!(
+            other_preview.component::<Name>(cloned_twice),
+            Some(&Name("John".into()))
+        );
+    }
+
+    #[test]
+    fn ecs_merge() {
+        let mut level = Ecs::new();
+        let player = level.insert((Player, Name("John".into())));
+
+        let chunk = std::thread::spawn(|| {
+            let mut chunk = Ecs::new();
+            chunk.insert((Enemy, Name("Goblin".into())));
+            let deleted = chunk.insert((Enemy, Name("Orc".into())));
+            chunk.insert((Enemy, Name("Troll".into())));
+            chunk.delete(deleted);
+            chunk
+        })
+        .join()
+        .unwrap();
+
+        let moved = level.merge(chunk);
+        assert_eq!(moved.len(), 2);
+        assert_eq!(level.entity_count(), 3);
+        assert_eq!(level.component::<Name>(player), Some(&Name("John".into())));
+
+        let mut names: Vec<_> = moved
+            .values()
+            .map(|&enemy| level.component::<Name>(enemy).unwrap().0.as_str())
+            .collect();
+        names.sort_unstable();
+        assert_eq!(names, ["Goblin", "Troll"]);
+    }
+
+    #[derive(Clone, Debug, Eq, PartialEq, Component)]
+    #[component(storage = "sparse_set")]
+    struct OnFire(u8);
+
+    #[test]
+    fn ecs_sparse_set_storage() {
+        let mut ecs = Ecs::new();
+        let entities = ecs.spawn_batch((0..10).map(|i| (Enemy, Health(i))));
+
+        for frame in 0..3 {
+            for &entity in &entities {
+                if entity.index % 3 == frame {
+                    ecs.entity_mut(entity)
+                        .insert(OnFire(frame.try_into().unwrap()));
+                } else {
+                    ecs.entity_mut(entity).remove::<OnFire>();
+                }
+            }
+        }
+
+        let burning: Vec<_> = ecs
+            .query::<(&Health, &OnFire)>()
+            .map(|(health, on_fire)| (health.0, on_fire.0))
+            .collect();
+        assert_eq!(burning, [(2, 2), (5, 2), (8, 2)]);
+
+        for (_, on_fire) in ecs.query::<(&Enemy, &mut OnFire)>() {
+            on_fire.0 = 0;
+        }
+        assert_eq!(ecs.component::<OnFire>(entities[5]), Some(&OnFire(0)));
+        assert_eq!(ecs.component::<OnFire>(entities[4]), None);
+    }
+
+    #[test]
+    fn ecs_sparse_set_transfer_and_clone() {
+        let mut simulation = Ecs::new();
+        let mut preview = Ecs::new();
+        simulation.register_clone::<OnFire>();
+
+        let first = simulation.insert((Enemy, OnFire(1)));
+        let second = simulation.insert((Enemy, OnFire(2)));
+        let third = simulation.insert((Enemy, OnFire(3)));
+
+        let cloned = simulation.clone_entity(third, &mut preview);
+        let moved = simulation.transfer(first, &mut preview);
+
+        assert_eq!(preview.component::<OnFire>(cloned), Some(&OnFire(3)));
+        assert_eq!(preview.component::<OnFire>(moved), Some(&OnFire(1)));
+        assert_eq!(simulation.component::<OnFire>(second), Some(&OnFire(2)));
+        assert_eq!(simulation.component::<OnFire>(third), Some(&OnFire(3)));
+        assert_eq!(simulation.query::<&OnFire>().count(), 2);
+    }
 }
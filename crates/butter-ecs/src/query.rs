@@ -1,6 +1,6 @@
-use std::marker::PhantomData;
+use std::{any::TypeId, marker::PhantomData, ops::Range};
 
-use super::Ecs;
+use super::{Component, Ecs, EntityIndex, StorageType};
 
 pub trait Description<'e> {
     type Item;
@@ -29,12 +29,72 @@ where
     }
 
     #[must_use]
-    pub fn iter(&self) -> Iter<D> {
+    pub fn iter(&self) -> Iter<'_, D> {
         Iter::new(self.ecs)
     }
+
+    /// Iterates over the runs of consecutive entities having all the
+    /// components, each chunk giving them as slices
+    ///
+    /// ```
+    /// # use butter_ecs::{query::Query, Component, Ecs};
+    /// # #[derive(Component)]
+    /// # struct Position(f32);
+    /// # #[derive(Component)]
+    /// # struct Velocity(f32);
+    /// # let mut ecs = Ecs::new();
+    /// # ecs.spawn_batch((0..10).map(|i| (Position(0.0), Velocity(i as f32))));
+    /// let query = Query::<(&mut Position, &Velocity)>::new(&ecs);
+    /// for chunk in query.chunks().unwrap() {
+    ///     let (positions, velocities) = chunk.components;
+    ///     for (position, velocity) in positions.iter_mut().zip(velocities) {
+    ///         position.0 += velocity.0;
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// Returns `None` if one of the components is stored in a sparse set,
+    /// whose components are not laid out by entity.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the query borrows a component more than once while
+    /// mutating it, like `(&mut Position, &Position)`, as the slices would
+    /// alias
+    #[must_use]
+    pub fn chunks(&self) -> Option<Chunks<'_, D>>
+    where
+        D: for<'d> ChunkDescription<'d>,
+    {
+        Chunks::new(self.ecs)
+    }
 }
 
-impl<'a, T: 'static> Description<'a> for &T {
+impl<'e, D> IntoIterator for Query<'e, D>
+where
+    D: for<'d> Description<'d>,
+{
+    type Item = <D as Description<'e>>::Item;
+    type IntoIter = Iter<'e, D>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Iter::new(self.ecs)
+    }
+}
+
+impl<'q, D> IntoIterator for &'q Query<'_, D>
+where
+    D: for<'d> Description<'d>,
+{
+    type Item = <D as Description<'q>>::Item;
+    type IntoIter = Iter<'q, D>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T: Component> Description<'a> for &T {
     type Item = &'a T;
 
     fn fetch(ecs: &'a Ecs, index: usize) -> Option<Self::Item> {
@@ -42,7 +102,7 @@ impl<'a, T: 'static> Description<'a> for &T {
     }
 }
 
-impl<'a, T: 'static> Description<'a> for &mut T {
+impl<'a, T: Component> Description<'a> for &mut T {
     type Item = &'a mut T;
 
     fn fetch(ecs: &'a Ecs, index: usize) -> Option<Self::Item> {
@@ -80,7 +140,10 @@ macro_rules ! gen_for_tuple {
     };
 }
 
-gen_for_tuple!(impl_query_description_for_tuple, [A, B, C, D, E, F, G, H, I, J, K, L, M, N]);
+gen_for_tuple!(
+    impl_query_description_for_tuple,
+    [A, B, C, D, E, F, G, H, I, J, K, L, M, N]
+);
 
 pub struct Iter<'a, Q>
 where
@@ -129,3 +192,268 @@ where
         next
     }
 }
+
+/// Describes queries whose components can be fetched as slices, see
+/// [`Query::chunks`]
+pub trait ChunkDescription<'e> {
+    type Chunk;
+
+    /// Whether the components can be laid out contiguously
+    fn is_contiguous() -> bool;
+
+    /// Adds the type of each component along with whether it is mutable
+    fn borrows(borrows: &mut Vec<(TypeId, bool)>);
+
+    fn matches(ecs: &Ecs, index: usize) -> bool;
+
+    /// # Safety
+    /// - Every entity in the range must match the query
+    /// - Mutable slices must not overlap with other slices of the same
+    ///   components
+    unsafe fn fetch_chunk(ecs: &'e Ecs, indices: Range<usize>) -> Self::Chunk;
+}
+
+impl<'a, T: Component> ChunkDescription<'a> for &T {
+    type Chunk = &'a [T];
+
+    fn is_contiguous() -> bool {
+        T::STORAGE == StorageType::Dense
+    }
+
+    fn borrows(borrows: &mut Vec<(TypeId, bool)>) {
+        borrows.push((TypeId::of::<T>(), false));
+    }
+
+    fn matches(ecs: &Ecs, index: usize) -> bool {
+        ecs.component_stores
+            .get(&TypeId::of::<T>())
+            .is_some_and(|store| store.contains(index))
+    }
+
+    unsafe fn fetch_chunk(ecs: &'a Ecs, indices: Range<usize>) -> Self::Chunk {
+        let store = ecs.component_stores.get(&TypeId::of::<T>()).unwrap();
+        store.slice(indices)
+    }
+}
+
+impl<'a, T: Component> ChunkDescription<'a> for &mut T {
+    type Chunk = &'a mut [T];
+
+    fn is_contiguous() -> bool {
+        T::STORAGE == StorageType::Dense
+    }
+
+    fn borrows(borrows: &mut Vec<(TypeId, bool)>) {
+        borrows.push((TypeId::of::<T>(), true));
+    }
+
+    fn matches(ecs: &Ecs, index: usize) -> bool {
+        <&T>::matches(ecs, index)
+    }
+
+    unsafe fn fetch_chunk(ecs: &'a Ecs, indices: Range<usize>) -> Self::Chunk {
+        let store = ecs.component_stores.get(&TypeId::of::<T>()).unwrap();
+        store.mark_changed(ecs.change_tick());
+        store.slice_mut(indices)
+    }
+}
+
+macro_rules! impl_chunk_description_for_tuple {
+    ($($t:tt,)*) => {
+        impl<'a, $($t),*> ChunkDescription<'a> for ($($t,)*)
+        where
+            $($t: 'static + ChunkDescription<'a>,)*
+        {
+            type Chunk = ($($t::Chunk,)*);
+
+            fn is_contiguous() -> bool {
+                $($t::is_contiguous())&&*
+            }
+
+            fn borrows(borrows: &mut Vec<(TypeId, bool)>) {
+                $($t::borrows(borrows);)*
+            }
+
+            fn matches(ecs: &Ecs, index: usize) -> bool {
+                $($t::matches(ecs, index))&&*
+            }
+
+            unsafe fn fetch_chunk(ecs: &'a Ecs, indices: Range<usize>) -> Self::Chunk {
+                ($($t::fetch_chunk(ecs, indices.clone()),)*)
+            }
+        }
+    };
+}
+
+gen_for_tuple!(
+    impl_chunk_description_for_tuple,
+    [A, B, C, D, E, F, G, H, I, J, K, L, M, N]
+);
+
+/// Components of consecutive entities, see [`Query::chunks`]
+pub struct Chunk<'a, C> {
+    /// Indices of the entities, the nth component of each slice belonging to
+    /// the nth entity
+    pub indices: Range<usize>,
+    pub components: C,
+    ecs: &'a Ecs,
+}
+
+impl<C> Chunk<'_, C> {
+    pub fn entities(&self) -> impl Iterator<Item = EntityIndex> + '_ {
+        self.indices
+            .clone()
+            .map(|index| self.ecs.entity_index_at(index))
+    }
+}
+
+pub struct Chunks<'a, D> {
+    ecs: &'a Ecs,
+    current_index: usize,
+    _marker: PhantomData<D>,
+}
+
+impl<'a, D> Chunks<'a, D>
+where
+    D: ChunkDescription<'a>,
+{
+    fn new(ecs: &'a Ecs) -> Option<Self> {
+        let mut borrows = vec![];
+        D::borrows(&mut borrows);
+        let aliased = borrows.iter().any(|&(type_id, mutable)| {
+            mutable
+                && borrows
+                    .iter()
+                    .filter(|&&(other_type_id, _)| other_type_id == type_id)
+                    .count()
+                    > 1
+        });
+        assert!(
+            !aliased,
+            "Query {} borrows a component more than once while mutating it",
+            std::any::type_name::<D>()
+        );
+
+        D::is_contiguous().then_some(Self {
+            ecs,
+            current_index: 0,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<'a, D> Iterator for Chunks<'a, D>
+where
+    D: ChunkDescription<'a>,
+{
+    type Item = Chunk<'a, D::Chunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let end = self.ecs.next_index;
+        while self.current_index < end && !D::matches(self.ecs, self.current_index) {
+            self.current_index += 1;
+        }
+        if self.current_index >= end {
+            return None;
+        }
+
+        let start = self.current_index;
+        while self.current_index < end && D::matches(self.ecs, self.current_index) {
+            self.current_index += 1;
+        }
+
+        let indices = start..self.current_index;
+        // SAFETY:
+        // - All the entities of the range match the query
+        // - The ranges of two chunks never overlap, and the mutable
+        //   components are borrowed once, as checked in `new`
+        let components = unsafe { D::fetch_chunk(self.ecs, indices.clone()) };
+        Some(Chunk {
+            indices,
+            components,
+            ecs: self.ecs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Component)]
+    struct Position(f32);
+    #[derive(Debug, PartialEq, Component)]
+    struct Velocity(f32);
+    #[derive(Component)]
+    #[component(storage = "sparse_set")]
+    struct Frozen;
+
+    #[test]
+    fn query_chunks() {
+        let mut ecs = Ecs::new();
+        let entities: Vec<_> = (0..6)
+            .map(|i: u8| ecs.insert((Position(0.0), Velocity(f32::from(i)))))
+            .collect();
+        ecs.entity_mut(entities[2]).remove::<Velocity>();
+        ecs.delete(entities[4]);
+
+        let query = Query::<(&mut Position, &Velocity)>::new(&ecs);
+        let mut chunks = vec![];
+        for chunk in query.chunks().unwrap() {
+            chunks.push(chunk.indices.clone());
+            let (positions, velocities) = chunk.components;
+            for (position, velocity) in positions.iter_mut().zip(velocities) {
+                position.0 += velocity.0;
+            }
+        }
+        assert_eq!(chunks, [0..2, 3..4, 5..6]);
+
+        let positions: Vec<_> = ecs
+            .query::<&Position>()
+            .map(|position| position.0)
+            .collect();
+        assert_eq!(positions, [0.0, 1.0, 0.0, 3.0, 5.0]);
+    }
+
+    #[test]
+    fn query_chunk_entities() {
+        let mut ecs = Ecs::new();
+        let first = ecs.insert((Position(0.0),));
+        ecs.delete(first);
+        let entities = ecs.spawn_batch((0..3).map(|i: u8| (Position(f32::from(i)),)));
+
+        let query = Query::<&Position>::new(&ecs);
+        let chunk = query.chunks().unwrap().next().unwrap();
+        assert_eq!(chunk.entities().collect::<Vec<_>>(), entities);
+        assert_eq!(
+            chunk.components,
+            [Position(0.0), Position(1.0), Position(2.0)]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "borrows a component more than once while mutating it")]
+    fn query_chunks_aliasing() {
+        let mut ecs = Ecs::new();
+        ecs.insert((Position(0.0),));
+        let query = Query::<(&mut Position, &Position)>::new(&ecs);
+        let _ = query.chunks();
+    }
+
+    #[test]
+    fn query_chunks_sharing() {
+        let mut ecs = Ecs::new();
+        ecs.insert((Position(1.0),));
+        let query = Query::<(&Position, &Position)>::new(&ecs);
+        let (positions, same_positions) = query.chunks().unwrap().next().unwrap().components;
+        assert_eq!(positions, same_positions);
+    }
+
+    #[test]
+    fn query_chunks_sparse_set() {
+        let mut ecs = Ecs::new();
+        ecs.insert((Position(0.0), Frozen));
+        let query = Query::<(&Position, &Frozen)>::new(&ecs);
+        assert!(query.chunks().is_none());
+    }
+}
@@ -0,0 +1,705 @@
+use std::{
+    any::{Any, TypeId},
+    cell::UnsafeCell,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicU64, Ordering},
+    thread::{self, ThreadId},
+};
+
+use crate::{
+    system::{Access, Parameter},
+    Ecs,
+};
+
+/// A resource along with the tick of its last modification
+pub(crate) struct ResourceCell {
+    value: UnsafeCell<Box<dyn Any + Send + Sync>>,
+    pub(crate) last_changed: AtomicU64,
+}
+
+impl ResourceCell {
+    pub(crate) fn new(value: Box<dyn Any + Send + Sync>, last_changed: u64) -> Self {
+        Self {
+            value: UnsafeCell::new(value),
+            last_changed: AtomicU64::new(last_changed),
+        }
+    }
+
+    pub(crate) fn value(&self) -> &(dyn Any + Send + Sync) {
+        // SAFETY:
+        // Mutable references are only handed out by `ResourceCell::value_mut`,
+        // whose callers ensure that there isn't any other reference
+        unsafe { &**self.value.get() }
+    }
+
+    /// # Safety
+    ///
+    /// The value must not be borrowed anywhere else while the returned
+    /// reference lives
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn value_mut(&self) -> &mut (dyn Any + Send + Sync) {
+        // SAFETY:
+        // Ensured by the caller
+        unsafe { &mut **self.value.get() }
+    }
+}
+
+// SAFETY:
+// Resources are required to be `Send` and `Sync`
+unsafe impl Sync for ResourceCell {}
+
+/// A resource which isn't `Send`, only accessible from the thread which
+/// inserted it
+pub(crate) struct NonSendCell {
+    value: UnsafeCell<Option<Box<dyn Any>>>,
+    thread_id: ThreadId,
+}
+
+impl NonSendCell {
+    fn new(value: Box<dyn Any>) -> Self {
+        Self {
+            value: UnsafeCell::new(Some(value)),
+            thread_id: thread::current().id(),
+        }
+    }
+
+    fn assert_owner_thread(&self, type_name: &str) {
+        assert!(
+            self.thread_id == thread::current().id(),
+            "Non-send resource {type_name} accessed from another thread than the one which inserted it"
+        );
+    }
+
+    fn value(&self, type_name: &str) -> &dyn Any {
+        self.assert_owner_thread(type_name);
+        // SAFETY:
+        // - See `ResourceCell::value`
+        // - The value is only ever accessed from its owner thread
+        unsafe {
+            (*self.value.get())
+                .as_deref()
+                .expect("The resource has been taken")
+        }
+    }
+
+    /// # Safety
+    ///
+    /// See [`ResourceCell::value_mut`]
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn value_mut(&self, type_name: &str) -> &mut dyn Any {
+        self.assert_owner_thread(type_name);
+        // SAFETY:
+        // - Ensured by the caller
+        // - The value is only ever accessed from its owner thread
+        unsafe {
+            (*self.value.get())
+                .as_deref_mut()
+                .expect("The resource has been taken")
+        }
+    }
+
+    fn into_inner(mut self, type_name: &str) -> Box<dyn Any> {
+        self.assert_owner_thread(type_name);
+        self.value
+            .get_mut()
+            .take()
+            .expect("The resource has been taken")
+    }
+}
+
+impl Drop for NonSendCell {
+    fn drop(&mut self) {
+        let Some(value) = self.value.get_mut().take() else {
+            return;
+        };
+        if self.thread_id == thread::current().id() {
+            drop(value);
+        } else {
+            // Dropping the value on another thread could break the
+            // invariants of its type, so it is leaked instead
+            std::mem::forget(value);
+        }
+    }
+}
+
+// SAFETY:
+// The value is only ever accessed and dropped from the thread which inserted
+// it, which is checked at runtime, otherwise it is leaked
+unsafe impl Send for NonSendCell {}
+unsafe impl Sync for NonSendCell {}
+
+pub(crate) type ResourceCloneFn = fn(&(dyn Any + Send + Sync)) -> Box<dyn Any + Send + Sync>;
+
+fn clone_resource_fn<R>(resource: &(dyn Any + Send + Sync)) -> Box<dyn Any + Send + Sync>
+where
+    R: Clone + Send + Sync + 'static,
+{
+    let resource = resource
+        .downcast_ref::<R>()
+        .expect("The resource doesn't have the type it was registered with");
+    Box::new(resource.clone())
+}
+
+/// Resources are values stored once in the [`Ecs`] instead of per entity,
+/// like a score or the settings of the game
+impl Ecs {
+    /// Stores a resource, returning the previous one of the same type
+    pub fn insert_resource<R>(&mut self, resource: R) -> Option<R>
+    where
+        R: Send + Sync + 'static,
+    {
+        let cell = ResourceCell::new(Box::new(resource), self.change_tick());
+        let previous = self.resources.insert(TypeId::of::<R>(), cell)?;
+        previous
+            .value
+            .into_inner()
+            .downcast()
+            .ok()
+            .map(|previous| *previous)
+    }
+
+    pub fn remove_resource<R>(&mut self) -> Option<R>
+    where
+        R: Send + Sync + 'static,
+    {
+        let cell = self.resources.remove(&TypeId::of::<R>())?;
+        cell.value
+            .into_inner()
+            .downcast()
+            .ok()
+            .map(|resource| *resource)
+    }
+
+    #[must_use]
+    pub fn contains_resource<R>(&self) -> bool
+    where
+        R: Send + Sync + 'static,
+    {
+        self.resources.contains_key(&TypeId::of::<R>())
+    }
+
+    #[must_use]
+    pub fn resource<R>(&self) -> Option<&R>
+    where
+        R: Send + Sync + 'static,
+    {
+        self.resources
+            .get(&TypeId::of::<R>())?
+            .value()
+            .downcast_ref()
+    }
+
+    #[must_use]
+    pub fn resource_mut<R>(&mut self) -> Option<&mut R>
+    where
+        R: Send + Sync + 'static,
+    {
+        // SAFETY:
+        // The `Ecs` is borrowed mutably
+        unsafe { self.resource_mut_unchecked() }
+    }
+
+    /// # Safety
+    ///
+    /// The resource must not be borrowed anywhere else while the returned
+    /// reference lives
+    #[allow(clippy::mut_from_ref)]
+    pub(crate) unsafe fn resource_mut_unchecked<R>(&self) -> Option<&mut R>
+    where
+        R: Send + Sync + 'static,
+    {
+        let cell = self.resources.get(&TypeId::of::<R>())?;
+        cell.last_changed
+            .store(self.change_tick(), Ordering::Relaxed);
+        // SAFETY:
+        // Ensured by the caller
+        unsafe { cell.value_mut() }.downcast_mut()
+    }
+
+    /// Lets the resources of type `R` be saved by [`Ecs::snapshot`]
+    pub fn register_clone_resource<R>(&mut self)
+    where
+        R: Clone + Send + Sync + 'static,
+    {
+        self.resource_clone_fns
+            .insert(TypeId::of::<R>(), clone_resource_fn::<R>);
+    }
+}
+
+/// Non-send resources can only be accessed from the thread which inserted
+/// them, the main thread of the application in practice. Accessing them from
+/// another thread panics, and dropping the `Ecs` on another thread leaks
+/// them. The thread which inserted the first one is the only one allowed to
+/// run the systems which are not [`Send`](crate::system::System::is_send).
+impl Ecs {
+    /// Stores a resource which isn't `Send`, returning the previous one of
+    /// the same type
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the previous resource was inserted by another thread
+    pub fn insert_non_send_resource<R>(&mut self, resource: R) -> Option<R>
+    where
+        R: 'static,
+    {
+        self.main_thread
+            .get_or_insert_with(|| thread::current().id());
+        let previous = self
+            .non_send_resources
+            .insert(TypeId::of::<R>(), NonSendCell::new(Box::new(resource)))?;
+        previous
+            .into_inner(std::any::type_name::<R>())
+            .downcast()
+            .ok()
+            .map(|previous| *previous)
+    }
+
+    /// # Panics
+    ///
+    /// Will panic if the resource was inserted by another thread
+    pub fn remove_non_send_resource<R>(&mut self) -> Option<R>
+    where
+        R: 'static,
+    {
+        let cell = self.non_send_resources.remove(&TypeId::of::<R>())?;
+        cell.into_inner(std::any::type_name::<R>())
+            .downcast()
+            .ok()
+            .map(|resource| *resource)
+    }
+
+    #[must_use]
+    pub fn contains_non_send_resource<R>(&self) -> bool
+    where
+        R: 'static,
+    {
+        self.non_send_resources.contains_key(&TypeId::of::<R>())
+    }
+
+    /// # Panics
+    ///
+    /// Will panic if the resource was inserted by another thread
+    #[must_use]
+    pub fn non_send_resource<R>(&self) -> Option<&R>
+    where
+        R: 'static,
+    {
+        self.non_send_resources
+            .get(&TypeId::of::<R>())?
+            .value(std::any::type_name::<R>())
+            .downcast_ref()
+    }
+
+    /// # Panics
+    ///
+    /// Will panic if the resource was inserted by another thread
+    #[must_use]
+    pub fn non_send_resource_mut<R>(&mut self) -> Option<&mut R>
+    where
+        R: 'static,
+    {
+        // SAFETY:
+        // The `Ecs` is borrowed mutably
+        unsafe { self.non_send_resource_mut_unchecked() }
+    }
+
+    /// # Safety
+    ///
+    /// See [`Ecs::resource_mut_unchecked`]
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the resource was inserted by another thread
+    #[allow(clippy::mut_from_ref)]
+    pub(crate) unsafe fn non_send_resource_mut_unchecked<R>(&self) -> Option<&mut R>
+    where
+        R: 'static,
+    {
+        let cell = self.non_send_resources.get(&TypeId::of::<R>())?;
+        // SAFETY:
+        // Ensured by the caller
+        unsafe { cell.value_mut(std::any::type_name::<R>()) }.downcast_mut()
+    }
+}
+
+/// Gives a system read access to a resource
+///
+/// # Panics
+///
+/// The system will panic when run if the resource doesn't exist
+pub struct Res<'ecs, R> {
+    value: &'ecs R,
+}
+
+impl<R> Deref for Res<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<R> Parameter for Res<'_, R>
+where
+    R: Send + Sync + 'static,
+{
+    type Type<'ecs> = Res<'ecs, R>;
+    type State = ();
+
+    fn init_state() -> Self::State {}
+
+    fn access(access: &mut Access) {
+        access.read_resource::<R>();
+    }
+
+    unsafe fn fetch<'a>(_state: &'a mut Self::State, ecs: &'a Ecs) -> Self::Type<'a> {
+        let value = ecs
+            .resource::<R>()
+            .unwrap_or_else(|| panic!("Resource {} does not exist", std::any::type_name::<R>()));
+        Res { value }
+    }
+}
+
+/// Gives a system mutable access to a resource, see [`Res`]
+pub struct ResMut<'ecs, R> {
+    value: &'ecs mut R,
+}
+
+impl<R> Deref for ResMut<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<R> DerefMut for ResMut<'_, R> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.value
+    }
+}
+
+impl<R> Parameter for ResMut<'_, R>
+where
+    R: Send + Sync + 'static,
+{
+    type Type<'ecs> = ResMut<'ecs, R>;
+    type State = ();
+
+    fn init_state() -> Self::State {}
+
+    fn access(access: &mut Access) {
+        access.write_resource::<R>();
+    }
+
+    unsafe fn fetch<'a>(_state: &'a mut Self::State, ecs: &'a Ecs) -> Self::Type<'a> {
+        // SAFETY:
+        // The caller ensures that the resource isn't borrowed anywhere else
+        let value = unsafe { ecs.resource_mut_unchecked::<R>() }
+            .unwrap_or_else(|| panic!("Resource {} does not exist", std::any::type_name::<R>()));
+        ResMut { value }
+    }
+}
+
+/// Gives a system read access to a non-send resource
+///
+/// Systems using it are not [`Send`](crate::system::System::is_send) and
+/// must run on the main thread.
+///
+/// # Panics
+///
+/// The system will panic when run if the resource doesn't exist or has been
+/// inserted by another thread
+pub struct NonSend<'ecs, R> {
+    value: &'ecs R,
+}
+
+impl<R> Deref for NonSend<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<R> Parameter for NonSend<'_, R>
+where
+    R: 'static,
+{
+    type Type<'ecs> = NonSend<'ecs, R>;
+    type State = ();
+    const IS_SEND: bool = false;
+
+    fn init_state() -> Self::State {}
+
+    fn access(access: &mut Access) {
+        access.read_non_send_resource::<R>();
+    }
+
+    unsafe fn fetch<'a>(_state: &'a mut Self::State, ecs: &'a Ecs) -> Self::Type<'a> {
+        let value = ecs.non_send_resource::<R>().unwrap_or_else(|| {
+            panic!(
+                "Non-send resource {} does not exist",
+                std::any::type_name::<R>()
+            )
+        });
+        NonSend { value }
+    }
+}
+
+/// Gives a system mutable access to a non-send resource, see [`NonSend`]
+pub struct NonSendMut<'ecs, R> {
+    value: &'ecs mut R,
+}
+
+impl<R> Deref for NonSendMut<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<R> DerefMut for NonSendMut<'_, R> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.value
+    }
+}
+
+impl<R> Parameter for NonSendMut<'_, R>
+where
+    R: 'static,
+{
+    type Type<'ecs> = NonSendMut<'ecs, R>;
+    type State = ();
+    const IS_SEND: bool = false;
+
+    fn init_state() -> Self::State {}
+
+    fn access(access: &mut Access) {
+        access.write_non_send_resource::<R>();
+    }
+
+    unsafe fn fetch<'a>(_state: &'a mut Self::State, ecs: &'a Ecs) -> Self::Type<'a> {
+        // SAFETY:
+        // The caller ensures that the resource isn't borrowed anywhere else
+        let value = unsafe { ecs.non_send_resource_mut_unchecked::<R>() }.unwrap_or_else(|| {
+            panic!(
+                "Non-send resource {} does not exist",
+                std::any::type_name::<R>()
+            )
+        });
+        NonSendMut { value }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        commands::Commands,
+        query::Query,
+        system::{Into, System},
+        Component,
+    };
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct Score(u32);
+
+    #[test]
+    fn ecs_resources() {
+        let mut ecs = Ecs::new();
+        assert!(!ecs.contains_resource::<Score>());
+        assert_eq!(ecs.insert_resource(Score(0)), None);
+        assert!(ecs.contains_resource::<Score>());
+
+        ecs.resource_mut::<Score>().unwrap().0 += 5;
+        assert_eq!(ecs.resource::<Score>(), Some(&Score(5)));
+
+        assert_eq!(ecs.insert_resource(Score(1)), Some(Score(5)));
+        assert_eq!(ecs.remove_resource::<Score>(), Some(Score(1)));
+        assert_eq!(ecs.resource::<Score>(), None);
+    }
+
+    #[test]
+    fn system_with_resource() {
+        #[derive(Component)]
+        struct Coin(u32);
+
+        #[allow(clippy::needless_pass_by_value)]
+        fn collect_coins(mut commands: Commands, score: Res<Score>, coins: Query<&Coin>) {
+            let total = coins.into_iter().map(|coin| coin.0).sum::<u32>() + score.0;
+            commands.insert((Coin(total),));
+        }
+
+        let mut ecs = Ecs::new();
+        ecs.insert_resource(Score(10));
+        ecs.insert((Coin(1),));
+        ecs.insert((Coin(2),));
+        ecs.run_single_system(&mut collect_coins.into_system());
+
+        assert_eq!(ecs.query::<&Coin>().map(|coin| coin.0).max(), Some(13));
+    }
+
+    #[test]
+    fn system_with_mutable_resource() {
+        fn add_points(mut score: ResMut<Score>) {
+            score.0 += 3;
+        }
+
+        let mut ecs = Ecs::new();
+        ecs.insert_resource(Score(1));
+        let mut system = add_points.into_system();
+        ecs.run_single_system(&mut system);
+        ecs.run_single_system(&mut system);
+        assert_eq!(ecs.resource::<Score>(), Some(&Score(7)));
+    }
+
+    // Raw pointers are neither `Send` nor `Sync`
+    struct WindowHandle(std::marker::PhantomData<*const u8>, u32);
+
+    #[test]
+    fn ecs_non_send_resources() {
+        let mut ecs = Ecs::new();
+        ecs.insert_non_send_resource(WindowHandle(std::marker::PhantomData, 1));
+        assert!(ecs.contains_non_send_resource::<WindowHandle>());
+
+        ecs.non_send_resource_mut::<WindowHandle>().unwrap().1 = 2;
+        assert_eq!(ecs.non_send_resource::<WindowHandle>().unwrap().1, 2);
+
+        // SAFETY:
+        // The resource isn't borrowed anywhere else
+        unsafe { NonSendMut::<WindowHandle>::fetch(&mut (), &ecs) }.1 += 1;
+        assert_eq!(ecs.remove_non_send_resource::<WindowHandle>().unwrap().1, 3);
+        assert!(!ecs.contains_non_send_resource::<WindowHandle>());
+    }
+
+    #[test]
+    fn system_with_non_send_resource() {
+        #[allow(clippy::needless_pass_by_value)]
+        fn read_window(mut commands: Commands, window: NonSend<WindowHandle>) {
+            commands.insert((Coin(window.1),));
+        }
+
+        #[derive(Component)]
+        struct Coin(u32);
+
+        let mut system = read_window.into_system();
+        assert!(!system.is_send());
+        assert!(collect_score.into_system().is_send());
+
+        let mut ecs = Ecs::new();
+        ecs.insert_non_send_resource(WindowHandle(std::marker::PhantomData, 7));
+        ecs.run_single_system(&mut system);
+        assert_eq!(ecs.query::<&Coin>().next().map(|coin| coin.0), Some(7));
+    }
+
+    fn collect_score(_score: Res<Score>) {}
+
+    #[test]
+    fn non_send_resource_from_another_thread() {
+        let mut ecs = Ecs::new();
+        ecs.insert_non_send_resource(WindowHandle(std::marker::PhantomData, 1));
+
+        let ecs = std::thread::spawn(move || {
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                ecs.non_send_resource::<WindowHandle>()
+                    .map(|window| window.1)
+            }));
+            assert!(result.is_err());
+            ecs
+        })
+        .join()
+        .unwrap();
+        assert_eq!(ecs.non_send_resource::<WindowHandle>().unwrap().1, 1);
+    }
+
+    #[test]
+    fn non_send_system_on_another_thread() {
+        #[allow(clippy::needless_pass_by_value)]
+        fn read_window(window: NonSend<WindowHandle>, mut score: ResMut<Score>) {
+            score.0 = window.1;
+        }
+
+        let mut ecs = Ecs::new();
+        ecs.insert_resource(Score(0));
+        ecs.insert_non_send_resource(WindowHandle(std::marker::PhantomData, 1));
+
+        let ecs = std::thread::spawn(move || {
+            let mut systems: Vec<Box<dyn System>> = vec![Box::new(read_window.into_system())];
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                ecs.run_systems(&mut systems);
+            }));
+            let message = result.unwrap_err().downcast::<String>().unwrap();
+            assert!(message.contains("can only run on the thread which inserted"));
+            ecs
+        })
+        .join()
+        .unwrap();
+        assert_eq!(ecs.resource::<Score>().unwrap().0, 0);
+        assert!(ecs.system_panics().is_empty());
+    }
+
+    #[test]
+    fn non_send_resource_dropped_on_another_thread() {
+        static DROPPED_ON: std::sync::Mutex<Vec<std::thread::ThreadId>> =
+            std::sync::Mutex::new(Vec::new());
+
+        struct Handle(std::marker::PhantomData<*const u8>);
+        impl Drop for Handle {
+            fn drop(&mut self) {
+                DROPPED_ON.lock().unwrap().push(std::thread::current().id());
+            }
+        }
+
+        let mut ecs = Ecs::new();
+        ecs.insert_non_send_resource(Handle(std::marker::PhantomData));
+        std::thread::spawn(move || drop(ecs)).join().unwrap();
+        assert!(DROPPED_ON.lock().unwrap().is_empty());
+
+        let mut ecs = Ecs::new();
+        ecs.insert_non_send_resource(Handle(std::marker::PhantomData));
+        let ecs = std::thread::spawn(move || ecs).join().unwrap();
+        drop(ecs);
+        assert_eq!(*DROPPED_ON.lock().unwrap(), [std::thread::current().id()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not exist")]
+    fn system_with_missing_resource() {
+        fn read_score(_score: Res<Score>) {}
+
+        let mut ecs = Ecs::new();
+        ecs.run_single_system(&mut read_score.into_system());
+    }
+
+    #[test]
+    fn system_with_shared_resources() {
+        #[allow(clippy::needless_pass_by_value)]
+        fn compare_scores(score: Res<Score>, other_score: Res<Score>, _window: NonSendMut<Score>) {
+            assert_eq!(score.0, other_score.0);
+        }
+
+        let mut ecs = Ecs::new();
+        ecs.insert_resource(Score(1));
+        ecs.insert_non_send_resource(Score(2));
+        ecs.run_single_system(&mut compare_scores.into_system());
+    }
+
+    #[test]
+    #[should_panic(expected = "more than once")]
+    fn system_with_conflicting_resources() {
+        fn add_points(_score: ResMut<Score>, _other_score: Res<Score>) {}
+
+        let _ = add_points.into_system();
+    }
+
+    #[test]
+    #[should_panic(expected = "more than once")]
+    fn system_with_conflicting_non_send_resources() {
+        fn move_window(_window: NonSendMut<WindowHandle>, _other_window: NonSendMut<WindowHandle>) {
+        }
+
+        let _ = move_window.into_system();
+    }
+}
@@ -0,0 +1,367 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::atomic::Ordering,
+};
+
+use crate::{
+    new_change_tick, resource::ResourceCell, resource::ResourceCloneFn, CloneFn, ComponentStore,
+    Ecs, EntityIndex,
+};
+
+/// A copy of the state of an [`Ecs`], see [`Ecs::snapshot`]
+pub struct Snapshot {
+    next_index: usize,
+    deleted_entities_indices: Vec<EntityIndex>,
+    generations: Vec<Option<usize>>,
+    clone_fns: HashMap<TypeId, CloneFn>,
+    /// In the order of the stores of the `Ecs`
+    component_stores: Vec<SavedStore>,
+    resource_clone_fns: HashMap<TypeId, ResourceCloneFn>,
+    resources: HashMap<TypeId, SavedResource>,
+}
+
+impl Snapshot {
+    fn saved_store(&self, type_id: &TypeId) -> Option<&SavedStore> {
+        self.component_stores
+            .iter()
+            .find(|saved_store| &saved_store.store.descriptor.type_id == type_id)
+    }
+}
+
+struct SavedStore {
+    store: ComponentStore,
+    last_changed: u64,
+}
+
+struct SavedResource {
+    value: Box<dyn Any + Send + Sync>,
+    last_changed: u64,
+}
+
+impl Ecs {
+    /// Saves the entities along with their components registered with
+    /// [`Ecs::register_clone`] and the resources registered with
+    /// [`Ecs::register_clone_resource`]
+    ///
+    /// The other components and resources are not part of the snapshot.
+    #[must_use]
+    pub fn snapshot(&self) -> Snapshot {
+        let component_stores = self
+            .component_stores
+            .iter()
+            .filter_map(|store| {
+                let clone_fn = *self.clone_fns.get(&store.descriptor.type_id)?;
+                // SAFETY:
+                // The clone function has been registered for the type of the store
+                Some(SavedStore {
+                    store: unsafe { store.clone_with(clone_fn) },
+                    last_changed: store.last_changed(),
+                })
+            })
+            .collect();
+
+        let resources = self
+            .resource_clone_fns
+            .iter()
+            .filter_map(|(type_id, clone_fn)| {
+                let cell = self.resources.get(type_id)?;
+                let saved_resource = SavedResource {
+                    value: clone_fn(cell.value()),
+                    last_changed: cell.last_changed.load(Ordering::Relaxed),
+                };
+                Some((*type_id, saved_resource))
+            })
+            .collect();
+
+        // Later modifications must not be mistaken for the saved state
+        self.change_tick.store(new_change_tick(), Ordering::Relaxed);
+
+        Snapshot {
+            next_index: self.next_index,
+            deleted_entities_indices: self.deleted_entities_indices.clone(),
+            generations: self.generations.clone(),
+            clone_fns: self.clone_fns.clone(),
+            component_stores,
+            resource_clone_fns: self.resource_clone_fns.clone(),
+            resources,
+        }
+    }
+
+    /// Brings the `Ecs` back to the state it had when the snapshot was taken
+    ///
+    /// Only the stores and resources modified since then are copied. The
+    /// components which are not part of the snapshot are kept for the
+    /// entities that exist in it and dropped for the other ones. No hook is
+    /// run.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        let change_tick = self.change_tick();
+        for store in &mut self.component_stores {
+            let type_id = &store.descriptor.type_id;
+            if snapshot.saved_store(type_id).is_some() {
+                continue;
+            }
+
+            if snapshot.clone_fns.contains_key(type_id) {
+                // There were no such components when the snapshot was taken
+                if store.count > 0 {
+                    store.clear();
+                    store.mark_changed(change_tick);
+                }
+                continue;
+            }
+
+            for (index, generation) in self.generations.iter().enumerate() {
+                let restored_generation = snapshot.generations.get(index).copied().flatten();
+                if generation.is_some() && *generation != restored_generation {
+                    store.remove(index);
+                    store.mark_changed(change_tick);
+                }
+            }
+        }
+        self.component_stores.remove_empty();
+
+        for saved_store in &snapshot.component_stores {
+            let type_id = saved_store.store.descriptor.type_id;
+            let clone_fn = snapshot.clone_fns[&type_id];
+            self.clone_fns.entry(type_id).or_insert(clone_fn);
+
+            let unchanged = self
+                .component_stores
+                .get(&type_id)
+                .is_some_and(|store| store.last_changed() == saved_store.last_changed);
+            if unchanged {
+                continue;
+            }
+
+            // SAFETY:
+            // The clone function has been registered for the type of the store
+            let store = unsafe { saved_store.store.clone_with(clone_fn) };
+            store.mark_changed(saved_store.last_changed);
+            self.component_stores.insert(store);
+        }
+
+        for (type_id, saved_resource) in &snapshot.resources {
+            let clone_fn = snapshot.resource_clone_fns[type_id];
+            self.resource_clone_fns.entry(*type_id).or_insert(clone_fn);
+
+            let unchanged = self.resources.get(type_id).is_some_and(|cell| {
+                cell.last_changed.load(Ordering::Relaxed) == saved_resource.last_changed
+            });
+            if unchanged {
+                continue;
+            }
+
+            let cell = ResourceCell::new(
+                clone_fn(saved_resource.value.as_ref()),
+                saved_resource.last_changed,
+            );
+            self.resources.insert(*type_id, cell);
+        }
+        self.resources.retain(|type_id, _| {
+            snapshot.resources.contains_key(type_id)
+                || !snapshot.resource_clone_fns.contains_key(type_id)
+        });
+
+        self.next_index = snapshot.next_index;
+        self.deleted_entities_indices
+            .clone_from(&snapshot.deleted_entities_indices);
+        self.generations.clone_from(&snapshot.generations);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+    use crate::Component;
+
+    #[derive(Clone, Debug, PartialEq, Component)]
+    struct Position(f32, f32);
+    #[derive(Clone, Debug, PartialEq, Component)]
+    struct Velocity(f32, f32);
+    #[derive(Clone, Debug, PartialEq, Eq, Component)]
+    #[component(storage = "sparse_set")]
+    struct Stunned;
+    #[derive(Debug, PartialEq, Eq, Component)]
+    struct Name(&'static str);
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct Frame(u64);
+
+    fn world() -> Ecs {
+        let mut ecs = Ecs::new();
+        ecs.register_clone::<Position>();
+        ecs.register_clone::<Velocity>();
+        ecs.register_clone::<Stunned>();
+        ecs.register_clone_resource::<Frame>();
+        ecs.insert_resource(Frame(0));
+        ecs
+    }
+
+    fn step(ecs: &mut Ecs) {
+        for (position, velocity) in ecs.query::<(&mut Position, &Velocity)>() {
+            position.0 += velocity.0;
+            position.1 += velocity.1;
+        }
+        ecs.resource_mut::<Frame>().unwrap().0 += 1;
+    }
+
+    type State = Vec<(EntityIndex, Option<Position>, Option<Velocity>, bool)>;
+
+    fn state(ecs: &Ecs) -> State {
+        ecs.entities()
+            .map(|entity| {
+                (
+                    entity,
+                    ecs.component::<Position>(entity).cloned(),
+                    ecs.component::<Velocity>(entity).cloned(),
+                    ecs.component::<Stunned>(entity).is_some(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ecs_restore_snapshot() {
+        let mut ecs = world();
+        let a = ecs.insert((Position(0.0, 0.0), Velocity(1.0, 0.0)));
+        let b = ecs.insert((Position(5.0, 5.0), Velocity(0.0, -1.0), Stunned));
+        let c = ecs.insert((Position(2.0, 2.0),));
+        ecs.delete(b);
+        step(&mut ecs);
+
+        let snapshot = ecs.snapshot();
+        let saved_state = state(&ecs);
+        let saved_query: Vec<_> = ecs
+            .query::<(&Position, &Velocity)>()
+            .map(|(position, velocity)| (position.clone(), velocity.clone()))
+            .collect();
+
+        ecs.delete(a);
+        ecs.entity_mut(c).insert((Velocity(3.0, 3.0), Stunned));
+        ecs.insert((Position(9.0, 9.0), Stunned));
+        step(&mut ecs);
+        assert_ne!(state(&ecs), saved_state);
+
+        ecs.restore(&snapshot);
+        assert_eq!(state(&ecs), saved_state);
+        assert_eq!(ecs.entity_count(), 2);
+        assert_eq!(ecs.query::<&Stunned>().count(), 0);
+        assert_eq!(ecs.resource::<Frame>(), Some(&Frame(1)));
+        let restored_query: Vec<_> = ecs
+            .query::<(&Position, &Velocity)>()
+            .map(|(position, velocity)| (position.clone(), velocity.clone()))
+            .collect();
+        assert_eq!(restored_query, saved_query);
+
+        // The allocator is restored too, reusing the index of the deleted entity
+        let d = ecs.insert((Position(0.0, 0.0),));
+        assert!(!ecs.contains(b));
+        assert_eq!(d.index, b.index);
+        assert_eq!(d.generation, b.generation + 1);
+    }
+
+    #[test]
+    fn ecs_restore_snapshot_twice() {
+        let mut ecs = world();
+        ecs.insert((Position(0.0, 0.0), Velocity(1.0, 1.0)));
+        let snapshot = ecs.snapshot();
+
+        step(&mut ecs);
+        let stepped_state = state(&ecs);
+
+        ecs.restore(&snapshot);
+        step(&mut ecs);
+        assert_eq!(state(&ecs), stepped_state);
+
+        ecs.restore(&snapshot);
+        assert_eq!(
+            ecs.component::<Position>(ecs.entities().next().unwrap()),
+            Some(&Position(0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn ecs_restore_only_copies_modified_stores() {
+        static CLONE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Debug, PartialEq, Eq, Component)]
+        struct Counted(u32);
+        impl Clone for Counted {
+            fn clone(&self) -> Self {
+                CLONE_COUNT.fetch_add(1, Ordering::Relaxed);
+                Self(self.0)
+            }
+        }
+
+        let mut ecs = world();
+        ecs.register_clone::<Counted>();
+        let entity = ecs.insert((Position(0.0, 0.0), Velocity(1.0, 0.0), Counted(3)));
+
+        let snapshot = ecs.snapshot();
+        assert_eq!(CLONE_COUNT.load(Ordering::Relaxed), 1);
+
+        step(&mut ecs);
+        ecs.restore(&snapshot);
+        assert_eq!(CLONE_COUNT.load(Ordering::Relaxed), 1);
+        assert_eq!(ecs.component::<Position>(entity), Some(&Position(0.0, 0.0)));
+
+        ecs.component_mut::<Counted>(entity).unwrap().0 = 4;
+        ecs.restore(&snapshot);
+        assert_eq!(CLONE_COUNT.load(Ordering::Relaxed), 2);
+        assert_eq!(ecs.component::<Counted>(entity), Some(&Counted(3)));
+    }
+
+    #[test]
+    fn ecs_restore_components_not_in_snapshot() {
+        let mut ecs = world();
+        let kept = ecs.insert((Position(0.0, 0.0), Name("kept")));
+        let snapshot = ecs.snapshot();
+
+        let deleted = ecs.insert((Position(1.0, 1.0), Name("deleted")));
+        ecs.restore(&snapshot);
+
+        assert!(!ecs.contains(deleted));
+        assert_eq!(ecs.component::<Name>(kept), Some(&Name("kept")));
+        assert_eq!(ecs.query::<&Name>().count(), 1);
+    }
+
+    #[test]
+    fn ecs_restore_resources() {
+        #[derive(Debug, PartialEq, Eq)]
+        struct Connection(u16);
+        #[derive(Clone, Debug, PartialEq, Eq)]
+        struct Score(u32);
+
+        let mut ecs = world();
+        ecs.register_clone_resource::<Score>();
+        ecs.insert_resource(Connection(1));
+        let snapshot = ecs.snapshot();
+
+        ecs.resource_mut::<Frame>().unwrap().0 = 10;
+        ecs.resource_mut::<Connection>().unwrap().0 = 2;
+        ecs.insert_resource(Score(3));
+        ecs.restore(&snapshot);
+
+        assert_eq!(ecs.resource::<Frame>(), Some(&Frame(0)));
+        assert_eq!(ecs.resource::<Connection>(), Some(&Connection(2)));
+        assert_eq!(ecs.resource::<Score>(), None);
+    }
+
+    #[test]
+    fn ecs_restore_into_another_ecs() {
+        let mut ecs = world();
+        ecs.insert((Position(0.0, 0.0), Velocity(1.0, 0.0)));
+        ecs.insert((Position(1.0, 0.0), Stunned));
+        let snapshot = ecs.snapshot();
+
+        let mut other = Ecs::new();
+        other.restore(&snapshot);
+        assert_eq!(state(&other), state(&ecs));
+
+        step(&mut ecs);
+        step(&mut other);
+        assert_eq!(state(&other), state(&ecs));
+    }
+}
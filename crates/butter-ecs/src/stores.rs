@@ -0,0 +1,99 @@
+use std::{any::TypeId, collections::HashMap};
+
+use crate::ComponentStore;
+
+/// The component stores of an [`Ecs`](crate::Ecs), kept in the order their
+/// type was first stored so that iterating over them is the same on every run
+pub(crate) struct ComponentStores {
+    stores: Vec<ComponentStore>,
+    indices: HashMap<TypeId, usize>,
+}
+
+impl ComponentStores {
+    pub fn new() -> Self {
+        Self {
+            stores: vec![],
+            indices: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, type_id: &TypeId) -> Option<&ComponentStore> {
+        self.indices.get(type_id).map(|&i| &self.stores[i])
+    }
+
+    pub fn get_mut(&mut self, type_id: &TypeId) -> Option<&mut ComponentStore> {
+        self.indices.get(type_id).map(|&i| &mut self.stores[i])
+    }
+
+    pub fn get_or_insert_with<F>(&mut self, type_id: TypeId, f: F) -> &mut ComponentStore
+    where
+        F: FnOnce() -> ComponentStore,
+    {
+        let i = *self.indices.entry(type_id).or_insert_with(|| {
+            self.stores.push(f());
+            self.stores.len() - 1
+        });
+        &mut self.stores[i]
+    }
+
+    /// Adds a store, replacing the one of the same type without changing its
+    /// position
+    pub fn insert(&mut self, store: ComponentStore) {
+        if let Some(&i) = self.indices.get(&store.descriptor.type_id) {
+            self.stores[i] = store;
+        } else {
+            self.indices
+                .insert(store.descriptor.type_id, self.stores.len());
+            self.stores.push(store);
+        }
+    }
+
+    /// Removes the stores which don't hold any component anymore
+    pub fn remove_empty(&mut self) {
+        if self.stores.iter().all(|store| store.count > 0) {
+            return;
+        }
+
+        self.stores.retain(|store| store.count > 0);
+        self.indices = self
+            .stores
+            .iter()
+            .enumerate()
+            .map(|(i, store)| (store.descriptor.type_id, i))
+            .collect();
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.remove_empty();
+        for store in &mut self.stores {
+            store.shrink_to_fit();
+        }
+        self.stores.shrink_to_fit();
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, ComponentStore> {
+        self.stores.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, ComponentStore> {
+        self.stores.iter_mut()
+    }
+}
+
+impl<'a> IntoIterator for &'a ComponentStores {
+    type Item = &'a ComponentStore;
+    type IntoIter = std::slice::Iter<'a, ComponentStore>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut ComponentStores {
+    type Item = &'a mut ComponentStore;
+    type IntoIter = std::slice::IterMut<'a, ComponentStore>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
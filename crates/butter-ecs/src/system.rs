@@ -1,42 +1,198 @@
-use std::{marker::PhantomData, ops::DerefMut};
+use std::{
+    any::TypeId,
+    borrow::Cow,
+    collections::{hash_map::Entry, HashMap},
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
 
-use crate::commands::CommandQueue;
+use crate::{commands::CommandQueue, diagnostics::SystemError};
 
 use super::{
     query::{Description, Query},
     Ecs,
 };
 
-pub trait System: 'static {
-    fn run(&mut self, ecs: &Ecs);
+/// Runs on the `Ecs`, taking an input of type `I` and returning an output of
+/// type `O`, which are `()` for the systems run by [`Ecs::run_systems`]
+pub trait System<I = (), O = ()>: 'static {
+    fn run(&mut self, input: I, ecs: &mut Ecs) -> O;
     fn command_queue(&mut self) -> &mut CommandQueue;
+
+    /// Drops the commands queued by a run which panicked, including the ones
+    /// still held by the parameters
+    fn discard_commands(&mut self) {
+        self.command_queue().drain().for_each(drop);
+    }
+
+    /// Whether the system can run on another thread than the main one, which
+    /// is not the case of the systems accessing non-send resources, as
+    /// checked by [`Ecs::run_systems`]
+    fn is_send(&self) -> bool {
+        true
+    }
+
+    /// Name used to report the panics, errors and timings of the system,
+    /// which is the path of its function by default
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed(std::any::type_name::<Self>())
+    }
 }
 
-impl System for Box<dyn System> {
-    fn run(&mut self, ecs: &Ecs) {
-        self.deref_mut().run(ecs);
+impl<I, O> System<I, O> for Box<dyn System<I, O>>
+where
+    I: 'static,
+    O: 'static,
+{
+    fn run(&mut self, input: I, ecs: &mut Ecs) -> O {
+        self.deref_mut().run(input, ecs)
     }
 
     fn command_queue(&mut self) -> &mut CommandQueue {
         self.deref_mut().command_queue()
     }
+
+    fn discard_commands(&mut self) {
+        self.deref_mut().discard_commands();
+    }
+
+    fn is_send(&self) -> bool {
+        self.deref().is_send()
+    }
+
+    fn name(&self) -> Cow<'static, str> {
+        self.deref().name()
+    }
+}
+
+/// Input of a system, given as the first parameter of its function
+///
+/// ```
+/// # use butter_ecs::system::{In, Into};
+/// fn read_direction() -> (i8, i8) {
+///     (1, 0)
+/// }
+///
+/// fn move_player(In(direction): In<(i8, i8)>) {
+///     assert_eq!(direction, (1, 0));
+/// }
+///
+/// let system = read_direction.pipe(move_player);
+/// ```
+pub struct In<T>(pub T);
+
+/// Signature of a function used as a system, `fn(A, B) -> O` or
+/// `fn(In<I>, A, B) -> O`
+pub trait FunctionSignature {
+    type Parameters: Parameter;
 }
 
 macro_rules! impl_system_for_fun {
     ($($p:tt,)*) => {
-        impl<FN, $($p),*> System for Function<FN, ($($p,)*)>
+        impl<Output, $($p),*> FunctionSignature for fn($($p,)*) -> Output
+        where
+            $($p: Parameter,)*
+        {
+            type Parameters = ($($p,)*);
+        }
+
+        impl<Input, Output, $($p),*> FunctionSignature for fn(In<Input>, $($p,)*) -> Output
+        where
+            $($p: Parameter,)*
+        {
+            type Parameters = ($($p,)*);
+        }
+
+        impl<FN, Output, $($p),*> System<(), Output> for Function<FN, fn($($p,)*) -> Output>
         where
-            FN: 'static + for<'ecs> FnMut(&mut CommandQueue, $(&$p::Type<'ecs>,)*),
+            FN: 'static + FnMut($($p,)*) -> Output + for<'ecs> FnMut($($p::Type<'ecs>,)*) -> Output,
+            Output: 'static,
             $($p: 'static + Parameter,)*
         {
-            #[allow(unused_variables)]
-            fn run(&mut self, ecs: &Ecs) {
-                (self.system_fn)(&mut self.command_queue, $(&$p::fetch(ecs),)*)
+            #[allow(unused_variables, unused_unsafe, non_snake_case)]
+            fn run(&mut self, _input: (), ecs: &mut Ecs) -> Output {
+                // Makes the call use the `FnMut` implementation taking the
+                // parameters fetched for this run
+                #[allow(clippy::too_many_arguments)]
+                fn call<Output, $($p,)*>(
+                    mut system_fn: impl FnMut($($p,)*) -> Output,
+                    $($p: $p,)*
+                ) -> Output {
+                    system_fn($($p,)*)
+                }
+
+                let ($($p,)*) = &mut self.state;
+                // SAFETY:
+                // - The accesses of the parameters have been checked not to
+                //   conflict when the system was built
+                // - The `Ecs` is borrowed mutably for the whole run
+                let output = unsafe { call(&mut self.system_fn, $($p::fetch($p, ecs),)*) };
+                <($($p,)*)>::queue_commands(&mut self.state, &mut self.command_queue);
+                output
             }
 
             fn command_queue(&mut self) -> &mut CommandQueue {
                 &mut self.command_queue
             }
+
+            fn discard_commands(&mut self) {
+                self.discard_commands();
+            }
+
+            fn is_send(&self) -> bool {
+                true $(&& $p::IS_SEND)*
+            }
+
+            fn name(&self) -> Cow<'static, str> {
+                Cow::Borrowed(std::any::type_name::<FN>())
+            }
+        }
+
+        impl<FN, Input, Output, $($p),*> System<Input, Output>
+            for Function<FN, fn(In<Input>, $($p,)*) -> Output>
+        where
+            FN: 'static
+                + FnMut(In<Input>, $($p,)*) -> Output
+                + for<'ecs> FnMut(In<Input>, $($p::Type<'ecs>,)*) -> Output,
+            Input: 'static,
+            Output: 'static,
+            $($p: 'static + Parameter,)*
+        {
+            #[allow(unused_variables, unused_unsafe, non_snake_case)]
+            fn run(&mut self, input: Input, ecs: &mut Ecs) -> Output {
+                // See the implementation for the functions without input
+                #[allow(clippy::too_many_arguments)]
+                fn call<Input, Output, $($p,)*>(
+                    mut system_fn: impl FnMut(In<Input>, $($p,)*) -> Output,
+                    input: Input,
+                    $($p: $p,)*
+                ) -> Output {
+                    system_fn(In(input), $($p,)*)
+                }
+
+                let ($($p,)*) = &mut self.state;
+                // SAFETY:
+                // See the implementation for the functions without input
+                let output = unsafe { call(&mut self.system_fn, input, $($p::fetch($p, ecs),)*) };
+                <($($p,)*)>::queue_commands(&mut self.state, &mut self.command_queue);
+                output
+            }
+
+            fn command_queue(&mut self) -> &mut CommandQueue {
+                &mut self.command_queue
+            }
+
+            fn discard_commands(&mut self) {
+                self.discard_commands();
+            }
+
+            fn is_send(&self) -> bool {
+                true $(&& $p::IS_SEND)*
+            }
+
+            fn name(&self) -> Cow<'static, str> {
+                Cow::Borrowed(std::any::type_name::<FN>())
+            }
         }
     };
 }
@@ -59,7 +215,29 @@ impl_system_for_fun!(A, B, C, D, E, F, G, H, I, J, K, L, M, N,);
 
 pub trait Parameter {
     type Type<'ecs>;
-    fn fetch(ecs: &Ecs) -> Self::Type<'_>;
+    /// Kept by the system between two runs, see [`Local`]
+    type State;
+
+    /// Whether the parameter can be fetched from another thread than the
+    /// main one
+    const IS_SEND: bool = true;
+
+    fn init_state() -> Self::State;
+
+    /// Registers the resources borrowed by the parameter, which is checked
+    /// when the system is built
+    fn access(_access: &mut Access) {}
+
+    /// # Safety
+    ///
+    /// The resources registered by [`Parameter::access`] must not be
+    /// borrowed anywhere else while the parameter lives, even immutably for
+    /// the ones it writes
+    unsafe fn fetch<'a>(state: &'a mut Self::State, ecs: &'a Ecs) -> Self::Type<'a>;
+
+    /// Moves the commands queued in the state while the system ran to the
+    /// queue executed by the `Ecs`, see [`Commands`](crate::commands::Commands)
+    fn queue_commands(_state: &mut Self::State, _command_queue: &mut CommandQueue) {}
 }
 
 macro_rules! impl_parameter_for_tuple {
@@ -67,10 +245,29 @@ macro_rules! impl_parameter_for_tuple {
         impl<$($t,)*> Parameter for ($($t,)*) where
         $($t: Parameter,)* {
             type Type<'ecs> = ($($t::Type<'ecs>,)*);
+            type State = ($($t::State,)*);
+            const IS_SEND: bool = true $(&& $t::IS_SEND)*;
+            #[allow(clippy::unused_unit)]
+            fn init_state() -> Self::State {
+                ($($t::init_state(),)*)
+            }
             #[allow(unused_variables)]
+            fn access(access: &mut Access) {
+                $($t::access(access);)*
+            }
+            #[allow(unused_variables, unused_unsafe, non_snake_case)]
             #[allow(clippy::unused_unit)]
-            fn fetch(ecs: &Ecs) -> Self::Type<'_> {
-                ($($t::fetch(ecs),)*)
+            unsafe fn fetch<'a>(state: &'a mut Self::State, ecs: &'a Ecs) -> Self::Type<'a> {
+                let ($($t,)*) = state;
+                // SAFETY:
+                // The accesses of the elements are part of the access of the
+                // tuple
+                unsafe { ($($t::fetch($t, ecs),)*) }
+            }
+            #[allow(unused_variables, non_snake_case)]
+            fn queue_commands(state: &mut Self::State, command_queue: &mut CommandQueue) {
+                let ($($t,)*) = state;
+                $($t::queue_commands($t, command_queue);)*
             }
         }
     };
@@ -92,39 +289,235 @@ impl_parameter_for_tuple!(A, B, C, D, E, F, G, H, I, J, K, L,);
 impl_parameter_for_tuple!(A, B, C, D, E, F, G, H, I, J, K, L, M,);
 impl_parameter_for_tuple!(A, B, C, D, E, F, G, H, I, J, K, L, M, N,);
 
+/// The resources borrowed by the parameters of a system, the same resource
+/// being either read by any number of parameters or written by a single one
+#[derive(Default)]
+pub struct Access {
+    /// Whether each resource is written, the non-send resources being told
+    /// apart from the other ones by their kind
+    borrows: HashMap<(ResourceKind, TypeId), bool>,
+    conflicts: Vec<&'static str>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum ResourceKind {
+    Send,
+    NonSend,
+}
+
+impl Access {
+    pub fn read_resource<R>(&mut self)
+    where
+        R: 'static,
+    {
+        self.borrow::<R>(ResourceKind::Send, false);
+    }
+
+    pub fn write_resource<R>(&mut self)
+    where
+        R: 'static,
+    {
+        self.borrow::<R>(ResourceKind::Send, true);
+    }
+
+    pub fn read_non_send_resource<R>(&mut self)
+    where
+        R: 'static,
+    {
+        self.borrow::<R>(ResourceKind::NonSend, false);
+    }
+
+    pub fn write_non_send_resource<R>(&mut self)
+    where
+        R: 'static,
+    {
+        self.borrow::<R>(ResourceKind::NonSend, true);
+    }
+
+    /// The names of the resources written by a parameter and borrowed by
+    /// another one
+    #[must_use]
+    pub fn conflicts(&self) -> &[&'static str] {
+        &self.conflicts
+    }
+
+    fn borrow<R>(&mut self, kind: ResourceKind, write: bool)
+    where
+        R: 'static,
+    {
+        match self.borrows.entry((kind, TypeId::of::<R>())) {
+            Entry::Occupied(mut entry) => {
+                if write || *entry.get() {
+                    self.conflicts.push(std::any::type_name::<R>());
+                }
+                *entry.get_mut() |= write;
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(write);
+            }
+        }
+    }
+}
+
 impl<D> Parameter for Query<'_, D>
 where
     D: for<'d> Description<'d>,
 {
     type Type<'ecs> = Query<'ecs, D>;
-    fn fetch(ecs: &Ecs) -> Self::Type<'_> {
+    type State = ();
+    fn init_state() -> Self::State {}
+    unsafe fn fetch<'a>(_state: &'a mut Self::State, ecs: &'a Ecs) -> Self::Type<'a> {
         Query::new(ecs)
     }
 }
 
-pub trait Into<P>
+/// State private to a system, kept between its runs
+///
+/// It starts with the default value of `T`, and each system using it gets
+/// its own.
+///
+/// ```
+/// # use butter_ecs::system::Local;
+/// fn count_frames(mut frame_count: Local<u64>) {
+///     *frame_count += 1;
+/// }
+/// ```
+pub struct Local<'s, T> {
+    value: &'s mut T,
+}
+
+impl<T> Deref for Local<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<T> DerefMut for Local<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.value
+    }
+}
+
+impl<T> Parameter for Local<'_, T>
 where
-    P: Parameter,
+    T: Default + Send + 'static,
 {
+    type Type<'s> = Local<'s, T>;
+    type State = T;
+
+    fn init_state() -> Self::State {
+        T::default()
+    }
+
+    unsafe fn fetch<'a>(state: &'a mut Self::State, _ecs: &'a Ecs) -> Self::Type<'a> {
+        Local { value: state }
+    }
+}
+
+/// Converts functions, closures and systems into systems
+///
+/// `M` only tells the implementations apart: it is the signature of the
+/// function, or [`IsSystem`] for the types which already are systems.
+pub trait Into<M> {
     type SystemType;
     fn into_system(self) -> Self::SystemType;
+
+    /// Gives the system another name than the path of its function, which is
+    /// mostly useful for closures
+    fn named<N>(self, name: N) -> Named<Self::SystemType>
+    where
+        Self: Sized,
+        N: std::convert::Into<Cow<'static, str>>,
+    {
+        Named {
+            system: self.into_system(),
+            name: name.into(),
+        }
+    }
+
+    /// Gives the output of this system as [`In`]put to another one, running
+    /// them one after the other
+    fn pipe<S, MS, I, T, O>(self, other: S) -> Pipe<Self::SystemType, S::SystemType, T>
+    where
+        Self: Sized,
+        Self::SystemType: System<I, T>,
+        S: Into<MS>,
+        S::SystemType: System<T, O>,
+    {
+        Pipe::new(self.into_system(), other.into_system())
+    }
+
+    /// Transforms the output of the system, like logging the error of a
+    /// system returning a `Result`
+    fn map<F, T, U>(self, f: F) -> Map<Self::SystemType, F, T>
+    where
+        Self: Sized,
+        F: FnMut(T) -> U,
+    {
+        Map {
+            system: self.into_system(),
+            f,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Only runs the system when the condition, a system returning a
+    /// `bool`, returns `true`, the output being `None` otherwise
+    fn run_if<C, MC>(self, condition: C) -> RunIf<Self::SystemType, C::SystemType>
+    where
+        Self: Sized,
+        C: Into<MC>,
+        C::SystemType: System<(), bool>,
+    {
+        RunIf {
+            system: self.into_system(),
+            condition: condition.into_system(),
+        }
+    }
+}
+
+/// Marks the implementation of [`Into`] for the types which already are
+/// systems, like `Box<dyn System>`
+pub struct IsSystem<I, O>(PhantomData<fn(I) -> O>);
+
+impl<S, I, O> Into<IsSystem<I, O>> for S
+where
+    S: System<I, O>,
+{
+    type SystemType = S;
+
+    fn into_system(self) -> Self::SystemType {
+        self
+    }
 }
 
 macro_rules! impl_into_for_fun {
     ($($t:tt,)*) => {
-        impl<FN, $($t,)*> Into<($($t,)*)> for FN
+        impl<FN, Output, $($t,)*> Into<fn($($t,)*) -> Output> for FN
         where
-            FN: 'static + FnMut(&mut CommandQueue, $(&$t,)*),
+            FN: 'static + FnMut($($t,)*) -> Output + for<'ecs> FnMut($($t::Type<'ecs>,)*) -> Output,
             $($t: Parameter,)*
         {
-            type SystemType = Function<FN, ($($t,)*)>;
+            type SystemType = Function<FN, fn($($t,)*) -> Output>;
 
             fn into_system(self) -> Self::SystemType {
-                Function {
-                    command_queue: CommandQueue::new(),
-                    system_fn: self,
-                    _marker: PhantomData,
-                }
+                Function::new(self)
+            }
+        }
+
+        impl<FN, Input, Output, $($t,)*> Into<fn(In<Input>, $($t,)*) -> Output> for FN
+        where
+            FN: 'static
+                + FnMut(In<Input>, $($t,)*) -> Output
+                + for<'ecs> FnMut(In<Input>, $($t::Type<'ecs>,)*) -> Output,
+            $($t: Parameter,)*
+        {
+            type SystemType = Function<FN, fn(In<Input>, $($t,)*) -> Output>;
+
+            fn into_system(self) -> Self::SystemType {
+                Function::new(self)
             }
         }
     };
@@ -146,13 +539,299 @@ impl_into_for_fun!(A, B, C, D, E, F, G, H, I, J, K, L,);
 impl_into_for_fun!(A, B, C, D, E, F, G, H, I, J, K, L, M,);
 impl_into_for_fun!(A, B, C, D, E, F, G, H, I, J, K, L, M, N,);
 
-pub struct Function<F, P>
+pub struct Function<F, M>
 where
-    P: Parameter,
+    M: FunctionSignature,
 {
     command_queue: CommandQueue,
+    state: <M::Parameters as Parameter>::State,
     system_fn: F,
-    _marker: PhantomData<P>,
+    _marker: PhantomData<fn() -> M>,
+}
+
+impl<F, M> Function<F, M>
+where
+    M: FunctionSignature,
+{
+    /// # Panics
+    ///
+    /// Will panic if a parameter writes a resource borrowed by another one
+    fn new(system_fn: F) -> Self {
+        let mut access = Access::default();
+        M::Parameters::access(&mut access);
+        assert!(
+            access.conflicts().is_empty(),
+            "System {} borrows the resources {:?} more than once while writing them",
+            std::any::type_name::<F>(),
+            access.conflicts()
+        );
+
+        Self {
+            command_queue: CommandQueue::new(),
+            state: M::Parameters::init_state(),
+            system_fn,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Drops the commands of the system and of its parameters, which keep
+    /// them when the system panics
+    fn discard_commands(&mut self) {
+        M::Parameters::queue_commands(&mut self.state, &mut self.command_queue);
+        self.command_queue.drain().for_each(drop);
+    }
+}
+
+/// A system with a custom name, see [`Into::named`]
+pub struct Named<S> {
+    system: S,
+    name: Cow<'static, str>,
+}
+
+impl<S, I, O> System<I, O> for Named<S>
+where
+    S: System<I, O>,
+{
+    fn run(&mut self, input: I, ecs: &mut Ecs) -> O {
+        self.system.run(input, ecs)
+    }
+
+    fn command_queue(&mut self) -> &mut CommandQueue {
+        self.system.command_queue()
+    }
+
+    fn discard_commands(&mut self) {
+        self.system.discard_commands();
+    }
+
+    fn is_send(&self) -> bool {
+        self.system.is_send()
+    }
+
+    fn name(&self) -> Cow<'static, str> {
+        self.name.clone()
+    }
+}
+
+/// Two systems run one after the other, the output of the first one being
+/// the input of the second one, see [`Into::pipe`]
+pub struct Pipe<A, B, T> {
+    first: A,
+    second: B,
+    name: Cow<'static, str>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<A, B, T> Pipe<A, B, T> {
+    fn new<I, O>(first: A, second: B) -> Self
+    where
+        A: System<I, T>,
+        B: System<T, O>,
+    {
+        let name = format!("{} | {}", first.name(), second.name());
+        Self {
+            first,
+            second,
+            name: Cow::Owned(name),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<A, B, I, T, O> System<I, O> for Pipe<A, B, T>
+where
+    A: System<I, T>,
+    B: System<T, O>,
+    T: 'static,
+{
+    fn run(&mut self, input: I, ecs: &mut Ecs) -> O {
+        let output = self.first.run(input, ecs);
+        // Keeps the commands in the order the systems queued them
+        self.second
+            .command_queue()
+            .extend(self.first.command_queue().drain());
+        self.second.run(output, ecs)
+    }
+
+    fn command_queue(&mut self) -> &mut CommandQueue {
+        self.second.command_queue()
+    }
+
+    fn discard_commands(&mut self) {
+        self.first.discard_commands();
+        self.second.discard_commands();
+    }
+
+    fn is_send(&self) -> bool {
+        self.first.is_send() && self.second.is_send()
+    }
+
+    fn name(&self) -> Cow<'static, str> {
+        self.name.clone()
+    }
+}
+
+/// A system whose output is transformed by a function, see [`Into::map`]
+pub struct Map<S, F, T> {
+    system: S,
+    f: F,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<S, F, I, T, U> System<I, U> for Map<S, F, T>
+where
+    S: System<I, T>,
+    F: 'static + FnMut(T) -> U,
+    T: 'static,
+{
+    fn run(&mut self, input: I, ecs: &mut Ecs) -> U {
+        (self.f)(self.system.run(input, ecs))
+    }
+
+    fn command_queue(&mut self) -> &mut CommandQueue {
+        self.system.command_queue()
+    }
+
+    fn discard_commands(&mut self) {
+        self.system.discard_commands();
+    }
+
+    fn is_send(&self) -> bool {
+        self.system.is_send()
+    }
+
+    fn name(&self) -> Cow<'static, str> {
+        self.system.name()
+    }
+}
+
+/// A system run depending on a condition, see [`Into::run_if`]
+pub struct RunIf<S, C> {
+    system: S,
+    condition: C,
+}
+
+impl<S, C, I, O> System<I, Option<O>> for RunIf<S, C>
+where
+    S: System<I, O>,
+    C: System<(), bool>,
+{
+    fn run(&mut self, input: I, ecs: &mut Ecs) -> Option<O> {
+        let run = self.condition.run((), ecs);
+        self.system
+            .command_queue()
+            .extend(self.condition.command_queue().drain());
+        run.then(|| self.system.run(input, ecs))
+    }
+
+    fn command_queue(&mut self) -> &mut CommandQueue {
+        self.system.command_queue()
+    }
+
+    fn discard_commands(&mut self) {
+        self.condition.discard_commands();
+        self.system.discard_commands();
+    }
+
+    fn is_send(&self) -> bool {
+        self.system.is_send() && self.condition.is_send()
+    }
+
+    fn name(&self) -> Cow<'static, str> {
+        self.system.name()
+    }
+}
+
+/// Output of the systems given to the engine, which are either infallible or
+/// return a `Result` whose error is reported by [`Ecs::system_errors`]
+pub trait Report: 'static {
+    fn into_error(self) -> Option<String>;
+}
+
+impl Report for () {
+    fn into_error(self) -> Option<String> {
+        None
+    }
+}
+
+impl<E> Report for Result<(), E>
+where
+    E: std::fmt::Display + 'static,
+{
+    fn into_error(self) -> Option<String> {
+        self.err().map(|error| error.to_string())
+    }
+}
+
+impl<R> Report for Option<R>
+where
+    R: Report,
+{
+    fn into_error(self) -> Option<String> {
+        self.and_then(Report::into_error)
+    }
+}
+
+/// A system whose errors are reported by [`Ecs::system_errors`], see
+/// [`Report`]
+pub struct Reported<S, O> {
+    system: S,
+    _marker: PhantomData<fn() -> O>,
+}
+
+impl<S, O> Reported<S, O>
+where
+    S: System<(), O>,
+    O: Report,
+{
+    pub fn new(system: S) -> Self {
+        Self {
+            system,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<S, O> System for Reported<S, O>
+where
+    S: System<(), O>,
+    O: Report,
+{
+    fn run(&mut self, input: (), ecs: &mut Ecs) {
+        if let Some(message) = self.system.run(input, ecs).into_error() {
+            let name = self.system.name();
+            self.system
+                .command_queue()
+                .push(move |ecs: &mut Ecs| ecs.system_errors.push(SystemError { name, message }));
+        }
+    }
+
+    fn command_queue(&mut self) -> &mut CommandQueue {
+        self.system.command_queue()
+    }
+
+    fn discard_commands(&mut self) {
+        self.system.discard_commands();
+    }
+
+    fn is_send(&self) -> bool {
+        self.system.is_send()
+    }
+
+    fn name(&self) -> Cow<'static, str> {
+        self.system.name()
+    }
+}
+
+/// What [`Ecs::run_systems`] does when a system panics
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PanicPolicy {
+    /// Panics again with the name of the system, stopping the frame
+    #[default]
+    Abort,
+    /// Discards the commands of the system and runs the next ones, the panic
+    /// being reported by [`Ecs::system_panics`]
+    Continue,
 }
 
 #[cfg(test)]
@@ -160,15 +839,21 @@ mod tests {
     use crate::query::Query;
 
     use super::*;
+    use crate::{
+        commands::Commands,
+        resource::{NonSend, Res, ResMut},
+        Component, SystemParam,
+    };
 
     #[test]
     fn system_with_single_query() {
-        #[derive(Debug, PartialEq, Eq)]
+        #[derive(Debug, PartialEq, Eq, Component)]
         struct Player;
-        #[derive(Debug, PartialEq, Eq)]
+        #[derive(Debug, PartialEq, Eq, Component)]
         struct Health(i16);
 
-        fn restore_player_health(_: &mut CommandQueue, query: &Query<(&Player, &mut Health)>) {
+        #[allow(clippy::needless_pass_by_value, clippy::explicit_iter_loop)]
+        fn restore_player_health(query: Query<(&Player, &mut Health)>) {
             for (_, health) in query.iter() {
                 health.0 = 10;
             }
@@ -186,17 +871,17 @@ mod tests {
 
     #[test]
     fn system_with_multiple_queries() {
-        #[derive(Debug, PartialEq, Eq)]
+        #[derive(Debug, PartialEq, Eq, Component)]
         struct Player;
-        #[derive(Debug, PartialEq, Eq)]
+        #[derive(Debug, PartialEq, Eq, Component)]
         struct Enemy;
-        #[derive(Debug, PartialEq, Eq)]
+        #[derive(Debug, PartialEq, Eq, Component)]
         struct Health(i16);
 
+        #[allow(clippy::needless_pass_by_value, clippy::explicit_iter_loop)]
         fn restore_player_health(
-            _: &mut CommandQueue,
-            query: &Query<(&Player, &mut Health)>,
-            query2: &Query<(&Enemy, &mut Health)>,
+            query: Query<(&Player, &mut Health)>,
+            query2: Query<(&Enemy, &mut Health)>,
         ) {
             for (_, health) in query.iter() {
                 health.0 = 10;
@@ -225,15 +910,16 @@ mod tests {
 
     #[test]
     fn system_inserting_entities() {
-        #[derive(Debug, PartialEq, Eq)]
+        #[derive(Debug, PartialEq, Eq, Component)]
         struct Player;
-        #[derive(Debug, PartialEq, Eq)]
+        #[derive(Debug, PartialEq, Eq, Component)]
         struct Enemy;
-        #[derive(Debug, PartialEq, Eq)]
+        #[derive(Debug, PartialEq, Eq, Component)]
         struct Health(i16);
-        fn insert_entities(command_queue: &mut CommandQueue) {
-            command_queue.insert((Player, Health(10)));
-            command_queue.insert((Enemy, Health(8)));
+
+        fn insert_entities(mut commands: Commands) {
+            commands.insert((Player, Health(10)));
+            commands.insert((Enemy, Health(8)));
         }
 
         let mut ecs = Ecs::new();
@@ -241,4 +927,240 @@ mod tests {
         ecs.run_single_system(&mut insert_entities.into_system());
         assert_eq!(ecs.entity_count(), 2);
     }
+
+    #[test]
+    fn system_with_derived_parameter() {
+        #[derive(Debug, PartialEq, Eq, Component)]
+        struct Player;
+        #[derive(Debug, PartialEq, Eq, Component)]
+        struct Enemy;
+        #[derive(Debug, PartialEq, Eq, Component)]
+        struct Health(i16);
+
+        #[derive(SystemParam)]
+        struct Characters<'ecs> {
+            players: Query<'ecs, (&'static Player, &'static mut Health)>,
+            enemies: Query<'ecs, (&'static Enemy, &'static mut Health)>,
+        }
+
+        fn swap_health(characters: Characters) {
+            for (_, health) in characters.players {
+                health.0 = 0;
+            }
+
+            for (_, health) in characters.enemies {
+                health.0 = 10;
+            }
+        }
+
+        #[derive(SystemParam)]
+        struct Window<'ecs> {
+            _handle: NonSend<'ecs, std::rc::Rc<()>>,
+            _characters: Characters<'ecs>,
+        }
+
+        fn draw(_window: Window) {}
+
+        assert!(swap_health.into_system().is_send());
+        assert!(!draw.into_system().is_send());
+
+        let mut ecs = Ecs::new();
+        ecs.insert((Player, Health(8)));
+        ecs.insert((Enemy, Health(3)));
+        ecs.run_single_system(&mut swap_health.into_system());
+
+        for (_, health) in ecs.query::<(&Player, &Health)>() {
+            assert_eq!(health, &Health(0));
+        }
+
+        for (_, health) in ecs.query::<(&Enemy, &Health)>() {
+            assert_eq!(health, &Health(10));
+        }
+    }
+
+    #[test]
+    fn system_with_local() {
+        #[derive(Debug, PartialEq, Eq, Component)]
+        struct Frame(u32);
+
+        #[derive(SystemParam)]
+        struct FrameCounter<'ecs> {
+            count: Local<'ecs, u32>,
+        }
+
+        fn count_frames(mut counter: FrameCounter, mut commands: Commands) {
+            *counter.count += 1;
+            commands.insert((Frame(*counter.count),));
+        }
+
+        fn skip_frames(mut skipped: Local<u32>) {
+            *skipped += 10;
+        }
+
+        let mut ecs = Ecs::new();
+        let mut first = count_frames.into_system();
+        let mut second = count_frames.into_system();
+        let mut skip = skip_frames.into_system();
+        for _ in 0..3 {
+            ecs.run_single_system(&mut first);
+            ecs.run_single_system(&mut skip);
+        }
+        ecs.run_single_system(&mut second);
+
+        let mut frames: Vec<_> = ecs.query::<&Frame>().map(|frame| frame.0).collect();
+        frames.sort_unstable();
+        assert_eq!(frames, [1, 1, 2, 3]);
+    }
+
+    #[test]
+    fn system_with_commands_in_any_position() {
+        #[derive(Debug, PartialEq, Eq, Component)]
+        struct Bullet(u8);
+        #[derive(Debug, PartialEq, Eq, Component)]
+        struct Gun(u8);
+
+        fn shoot(guns: Query<&mut Gun>, mut commands: Commands) {
+            for gun in guns {
+                commands.insert((Bullet(gun.0),));
+                gun.0 -= 1;
+            }
+        }
+
+        let mut ecs = Ecs::new();
+        ecs.insert((Gun(2),));
+        let mut system = shoot.into_system();
+        ecs.run_single_system(&mut system);
+        ecs.run_single_system(&mut system);
+
+        let mut bullets: Vec<_> = ecs.query::<&Bullet>().map(|bullet| bullet.0).collect();
+        bullets.sort_unstable();
+        assert_eq!(bullets, [1, 2]);
+        assert_eq!(ecs.query::<&Gun>().next(), Some(&Gun(0)));
+    }
+
+    #[test]
+    fn closures_and_trait_objects_as_systems() {
+        #[derive(Debug, PartialEq, Eq, Component)]
+        struct Score(u32);
+
+        let bonus = 5;
+        let closure = move |mut commands: Commands| {
+            commands.insert((Score(bonus),));
+        };
+        let named = (|mut commands: Commands| {
+            commands.insert((Score(1),));
+        })
+        .named("insert_one");
+        assert_eq!(named.name(), "insert_one");
+
+        let boxed: Box<dyn System> = Box::new(closure.into_system());
+        assert!(boxed.name().contains("closure"));
+        let mut systems: Vec<Box<dyn System>> = vec![Box::new(boxed.into_system())];
+        systems.push(Box::new(named.into_system()));
+
+        let mut ecs = Ecs::new();
+        ecs.run_systems(&mut systems);
+        let mut scores: Vec<_> = ecs.query::<&Score>().map(|score| score.0).collect();
+        scores.sort_unstable();
+        assert_eq!(scores, [1, 5]);
+    }
+
+    #[test]
+    fn piped_systems() {
+        #[derive(Debug, PartialEq, Eq, Component)]
+        struct Player;
+        #[derive(Debug, PartialEq, Eq, Component)]
+        struct Enemy(usize);
+
+        fn count_players(players: Query<&Player>, mut commands: Commands) -> usize {
+            commands.insert((Player,));
+            players.into_iter().count()
+        }
+
+        fn spawn_enemies(In(count): In<usize>, mut commands: Commands) -> usize {
+            for i in 0..count {
+                commands.insert((Enemy(i),));
+            }
+            count * 10
+        }
+
+        let mut system = count_players.pipe(spawn_enemies);
+        assert_eq!(
+            system.name(),
+            format!(
+                "{} | {}",
+                std::any::type_name_of_val(&count_players),
+                std::any::type_name_of_val(&spawn_enemies)
+            )
+        );
+
+        let mut ecs = Ecs::new();
+        assert_eq!(ecs.run_single_system(&mut system), 0);
+        assert_eq!(ecs.run_single_system(&mut system), 10);
+        assert_eq!(ecs.run_single_system(&mut system), 20);
+
+        // The player inserted by the first system is queued before the
+        // enemies of the second one
+        let entities: Vec<_> = ecs.entities().collect();
+        assert!(ecs.component::<Player>(entities[1]).is_some());
+        assert!(ecs.component::<Enemy>(entities[2]).is_some());
+        assert_eq!(ecs.query::<&Enemy>().count(), 3);
+    }
+
+    #[test]
+    fn conditional_systems() {
+        struct Paused(bool);
+        struct Score(u32);
+
+        fn score(mut score: ResMut<Score>) -> Result<(), String> {
+            score.0 += 1;
+            Err("Scored".to_string())
+        }
+
+        let mut system = Reported::new(score.run_if(|paused: Res<Paused>| !paused.0));
+        let mut ecs = Ecs::new();
+        ecs.insert_resource(Paused(false));
+        ecs.insert_resource(Score(0));
+        ecs.run_single_system(&mut system);
+        assert_eq!(ecs.resource::<Score>().unwrap().0, 1);
+        assert_eq!(ecs.system_errors().len(), 1);
+
+        ecs.resource_mut::<Paused>().unwrap().0 = true;
+        ecs.run_single_system(&mut system);
+        assert_eq!(ecs.resource::<Score>().unwrap().0, 1);
+        assert_eq!(ecs.system_errors().len(), 1);
+    }
+
+    #[test]
+    fn mapped_and_reported_systems() {
+        fn load_level(mut attempts: Local<u32>) -> Result<(), String> {
+            *attempts += 1;
+            if *attempts == 1 {
+                Err("level.toml not found".to_string())
+            } else {
+                Ok(())
+            }
+        }
+
+        let mut is_err = load_level.map(|result: Result<(), String>| result.is_err());
+        let mut ecs = Ecs::new();
+        assert!(ecs.run_single_system(&mut is_err));
+        assert!(!ecs.run_single_system(&mut is_err));
+
+        let mut systems: Vec<Box<dyn System>> =
+            vec![Box::new(Reported::new(load_level.into_system()))];
+        ecs.run_systems(&mut systems);
+        assert_eq!(
+            ecs.system_errors(),
+            [SystemError {
+                name: std::any::type_name_of_val(&load_level).into(),
+                message: "level.toml not found".to_string(),
+            }]
+        );
+        ecs.run_systems(&mut systems);
+        assert_eq!(ecs.system_errors().len(), 1);
+        ecs.clear_system_diagnostics();
+        ecs.run_systems(&mut systems);
+        assert!(ecs.system_errors().is_empty());
+    }
 }
@@ -2,19 +2,57 @@
 
 use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
 
+pub use wgpu::PowerPreference;
+
+/// How the frames are rendered, the vsync being chosen per surface
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Options {
+    /// Samples per pixel of the multisample anti-aliasing, 1 disabling it
+    pub msaa_samples: u32,
+    pub power_preference: PowerPreference,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            msaa_samples: 1,
+            power_preference: PowerPreference::default(),
+        }
+    }
+}
+
+/// The GPU state shared by the surfaces of every window
 pub struct State {
-    surface: wgpu::Surface,
+    instance: wgpu::Instance,
+    adapter: wgpu::Adapter,
     device: wgpu::Device,
     queue: wgpu::Queue,
-    _config: wgpu::SurfaceConfiguration,
-    _size: (u32, u32),
+    options: Options,
+}
+
+/// What a window is rendered to
+pub struct Surface {
+    inner: wgpu::Surface,
+    config: wgpu::SurfaceConfiguration,
+    msaa_samples: u32,
+    /// The multisampled texture rendered to then resolved to the surface,
+    /// when the anti-aliasing is enabled
+    msaa_view: Option<wgpu::TextureView>,
 }
 
 impl State {
+    /// Picks a GPU able to render to the first window with the given
+    /// options, returning the surface of that window along with the state
+    ///
     /// # Panics
     ///
     /// Will panic if no adapter is available or no suitable device is available
-    pub async fn new<W>(window: &W, size: (u32, u32)) -> Self
+    pub async fn new<W>(
+        window: &W,
+        size: (u32, u32),
+        vsync: bool,
+        options: Options,
+    ) -> (Self, Surface)
     where
         W: HasRawWindowHandle + HasRawDisplayHandle,
     {
@@ -22,7 +60,7 @@ impl State {
         let surface = unsafe { instance.create_surface(window) };
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
+                power_preference: options.power_preference,
                 compatible_surface: Some(&surface),
                 force_fallback_adapter: false,
             })
@@ -45,31 +83,96 @@ impl State {
             .await
             .unwrap();
 
+        let state = Self {
+            instance,
+            adapter,
+            device,
+            queue,
+            options,
+        };
+        let surface = state.configure_surface(surface, size, vsync);
+        (state, surface)
+    }
+
+    /// Creates the surface of another window, which must outlive it, without
+    /// anti-aliasing if the GPU doesn't support the requested samples for its
+    /// format
+    pub fn create_surface<W>(&self, window: &W, size: (u32, u32), vsync: bool) -> Surface
+    where
+        W: HasRawWindowHandle + HasRawDisplayHandle,
+    {
+        let surface = unsafe { self.instance.create_surface(window) };
+        self.configure_surface(surface, size, vsync)
+    }
+
+    fn configure_surface(&self, surface: wgpu::Surface, size: (u32, u32), vsync: bool) -> Surface {
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface.get_supported_formats(&adapter)[0],
+            format: surface.get_supported_formats(&self.adapter)[0],
             width: size.0,
             height: size.1,
-            present_mode: wgpu::PresentMode::Fifo,
+            present_mode: present_mode(vsync),
             alpha_mode: wgpu::CompositeAlphaMode::Auto,
         };
 
-        surface.configure(&device, &config);
+        surface.configure(&self.device, &config);
 
-        Self {
-            surface,
-            device,
-            queue,
-            _config: config,
-            _size: size,
+        let msaa_flags = wgpu::TextureFormatFeatureFlags::MULTISAMPLE
+            | wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE;
+        let msaa_supported = self
+            .adapter
+            .get_texture_format_features(config.format)
+            .flags
+            .contains(msaa_flags);
+        let msaa_samples = if msaa_supported {
+            self.options.msaa_samples
+        } else {
+            1
+        };
+        let msaa_view = msaa_view(&self.device, &config, msaa_samples);
+
+        Surface {
+            inner: surface,
+            config,
+            msaa_samples,
+            msaa_view,
         }
     }
 
+    /// Reconfigures the surface for the new size of its window, ignoring
+    /// empty sizes like the ones of minimized windows
+    pub fn resize(&self, surface: &mut Surface, size: (u32, u32)) {
+        if size.0 > 0 && size.1 > 0 {
+            surface.config.width = size.0;
+            surface.config.height = size.1;
+            surface.inner.configure(&self.device, &surface.config);
+            surface.msaa_view = msaa_view(&self.device, &surface.config, surface.msaa_samples);
+        }
+    }
+
+    /// Reconfigures the surface to wait for the refresh of the screen or not
+    pub fn set_vsync(&self, surface: &mut Surface, vsync: bool) {
+        surface.config.present_mode = present_mode(vsync);
+        surface.inner.configure(&self.device, &surface.config);
+    }
+
     /// # Panics
     ///
-    /// Might panic if getting the next texture to be presented by the swapchain for drawing fails
-    pub fn render(&mut self) {
-        let output = self.surface.get_current_texture().unwrap();
+    /// Will panic if there is not enough memory to get the next texture to
+    /// be presented by the swapchain
+    pub fn render(&self, surface: &mut Surface) {
+        let output = match surface.inner.get_current_texture() {
+            Ok(output) => output,
+            // The frame is skipped while the surface is reconfigured
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                surface.inner.configure(&self.device, &surface.config);
+                return;
+            }
+            // Or when the texture isn't ready in time, like while the window
+            // is hidden on some platforms
+            Err(wgpu::SurfaceError::Timeout) => return,
+            Err(error) => panic!("{error}"),
+        };
         let view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
@@ -82,8 +185,8 @@ impl State {
             let _render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
+                    view: surface.msaa_view.as_ref().unwrap_or(&view),
+                    resolve_target: surface.msaa_view.as_ref().map(|_| &view),
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color {
                             r: 0.1,
@@ -103,3 +206,44 @@ impl State {
         output.present();
     }
 }
+
+impl Surface {
+    #[must_use]
+    pub fn size(&self) -> (u32, u32) {
+        (self.config.width, self.config.height)
+    }
+}
+
+/// Creates the multisampled texture of the size of the surface, if there is
+/// more than one sample
+fn msaa_view(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    msaa_samples: u32,
+) -> Option<wgpu::TextureView> {
+    (msaa_samples > 1).then(|| {
+        device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("msaa"),
+                size: wgpu::Extent3d {
+                    width: config.width,
+                    height: config.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: msaa_samples,
+                dimension: wgpu::TextureDimension::D2,
+                format: config.format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default())
+    })
+}
+
+fn present_mode(vsync: bool) -> wgpu::PresentMode {
+    if vsync {
+        wgpu::PresentMode::AutoVsync
+    } else {
+        wgpu::PresentMode::AutoNoVsync
+    }
+}
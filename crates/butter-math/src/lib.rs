@@ -1,6 +1,7 @@
 #![warn(clippy::pedantic)]
 #![allow(clippy::module_name_repetitions)]
 
+#[cfg(test)]
 #[macro_use]
 extern crate assert_float_eq;
 
@@ -1,12 +1,16 @@
-use butter::ecs::commands::CommandQueue;
+use butter::ecs::commands::Commands;
 use butter::ecs::query::Query;
+use butter::ecs::Component;
+use butter::plugin::DefaultPlugins;
 use butter::ButterEngineBuilder;
 
+#[derive(Component)]
 pub struct Player(&'static str);
 
 fn main() {
     let engine = ButterEngineBuilder::new()
         .with_window_title("Window")
+        .with_plugins(DefaultPlugins)
         .with_init_system(init)
         .with_system(hello_world)
         .with_system(hello_player)
@@ -14,17 +18,17 @@ fn main() {
     butter::winit::ButterRunner::run(engine);
 }
 
-fn init(command_queue: &mut CommandQueue) {
-    command_queue.insert((Player("John Doe"),));
-    command_queue.insert((Player("Jack Doe"),));
+fn init(mut commands: Commands) {
+    commands.insert((Player("John Doe"),));
+    commands.insert((Player("Jack Doe"),));
 }
 
-fn hello_world(_: &mut CommandQueue) {
+fn hello_world() {
     println!("hello world");
 }
 
-fn hello_player(_: &mut CommandQueue, players: &Query<(&Player,)>) {
-    for (player,) in players.iter() {
+fn hello_player(players: Query<&Player>) {
+    for player in players {
         println!("hello {}", player.0);
     }
 }
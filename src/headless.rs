@@ -0,0 +1,163 @@
+use web_time::{Duration, Instant};
+
+use crate::ButterEngine;
+
+/// Runs the engine without a window nor rendering, like on a dedicated
+/// server or in integration tests
+///
+/// It stops after the given number of frames, or when an
+/// [`AppExit`](crate::AppExit) event is sent, then runs the shutdown systems.
+#[derive(Default)]
+pub struct HeadlessRunner {
+    frames: Option<u64>,
+    frame_duration: Option<Duration>,
+}
+
+impl HeadlessRunner {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_frames(&mut self, frames: u64) -> &mut Self {
+        self.frames = Some(frames);
+        self
+    }
+
+    /// Limits the number of updates per second, which are run as fast as
+    /// possible by default
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `ticks_per_second` is not strictly positive
+    pub fn with_tick_rate(&mut self, ticks_per_second: f64) -> &mut Self {
+        assert!(
+            ticks_per_second > 0.0,
+            "The tick rate must be strictly positive"
+        );
+        self.frame_duration = Some(Duration::from_secs_f64(1.0 / ticks_per_second));
+        self
+    }
+
+    /// Runs the init systems then updates the engine until it should stop,
+    /// and shuts it down, returning it for inspection
+    ///
+    /// The tick rate relies on `std::thread::sleep`, which isn't available
+    /// on the web.
+    pub fn run(&self, mut engine: ButterEngine) -> ButterEngine {
+        engine.init();
+
+        let mut frame = 0;
+        while self.frames.is_none_or(|frames| frame < frames) {
+            let start = Instant::now();
+            engine.update();
+            frame += 1;
+            if engine.should_exit() {
+                break;
+            }
+
+            if let Some(frame_duration) = self.frame_duration {
+                std::thread::sleep(frame_duration.saturating_sub(start.elapsed()));
+            }
+        }
+        engine.shutdown();
+
+        engine
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{
+        ecs::{
+            diagnostics::SystemError,
+            event::EventWriter,
+            resource::ResMut,
+            system::{Into, Local},
+        },
+        AppExit, ButterEngineBuilder,
+    };
+
+    struct Frames(u64);
+
+    fn count_frames(mut frames: ResMut<Frames>) {
+        frames.0 += 1;
+    }
+
+    #[test]
+    fn headless_runner_frames() {
+        let engine = ButterEngineBuilder::new()
+            .with_resource(Frames(0))
+            .with_system(count_frames)
+            .build();
+        let engine = HeadlessRunner::new()
+            .with_frames(5)
+            .with_tick_rate(1000.0)
+            .run(engine);
+        assert_eq!(engine.ecs().resource::<Frames>().unwrap().0, 5);
+    }
+
+    #[test]
+    fn headless_runner_exit() {
+        fn exit_after_three(mut frame: Local<u32>, mut exit: EventWriter<AppExit>) {
+            *frame += 1;
+            if *frame == 3 {
+                exit.send(AppExit);
+            }
+        }
+
+        fn save(mut frames: ResMut<Frames>) {
+            frames.0 *= 10;
+        }
+
+        let engine = ButterEngineBuilder::new()
+            .with_resource(Frames(0))
+            .with_system(count_frames)
+            .with_system(exit_after_three.named("exit"))
+            .with_shutdown_system(save)
+            .build();
+        let mut engine = HeadlessRunner::new().run(engine);
+        assert_eq!(engine.ecs().resource::<Frames>().unwrap().0, 30);
+
+        // The shutdown systems only run once
+        engine.shutdown();
+        assert_eq!(engine.ecs().resource::<Frames>().unwrap().0, 30);
+    }
+
+    #[test]
+    fn headless_runner_system_errors() {
+        static FAILURES: AtomicUsize = AtomicUsize::new(0);
+
+        fn fail() -> Result<(), String> {
+            Err("no save file".to_string())
+        }
+
+        fn count_failures(system_error: &SystemError) {
+            assert_eq!(system_error.message, "no save file");
+            FAILURES.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let engine = ButterEngineBuilder::new()
+            .with_system(fail)
+            .with_system_error_handler(count_failures)
+            .build();
+        HeadlessRunner::new().with_frames(3).run(engine);
+        assert_eq!(FAILURES.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn manual_updates() {
+        let mut engine = ButterEngineBuilder::new()
+            .with_resource(Frames(0))
+            .with_system(count_frames)
+            .build();
+        engine.init();
+        engine.update();
+        engine.update();
+        assert_eq!(engine.ecs().resource::<Frames>().unwrap().0, 2);
+        assert!(!engine.should_exit());
+    }
+}
@@ -0,0 +1,564 @@
+use std::{collections::HashMap, fmt, fs, hash::Hash, io, path::Path};
+
+use butter_math::vector::Vector2;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    ecs::resource::{Res, ResMut},
+    plugin::Plugin,
+    ButterEngineBuilder,
+};
+
+use super::{
+    Axis, GamepadAxis, GamepadAxisType, GamepadButton, GamepadButtonType, Gamepads, Input, KeyCode,
+    MouseButton,
+};
+
+/// A type whose values name the actions of the game, usually an enum
+///
+/// ```
+/// # use serde::{Deserialize, Serialize};
+/// #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// enum Action {
+///     Jump,
+///     Move,
+/// }
+/// ```
+pub trait Actionlike:
+    Copy + Eq + Hash + Send + Sync + Serialize + DeserializeOwned + 'static
+{
+}
+
+impl<A> Actionlike for A where
+    A: Copy + Eq + Hash + Send + Sync + Serialize + DeserializeOwned + 'static
+{
+}
+
+/// A button triggering an action, on the keyboard, the mouse or any
+/// connected gamepad
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType),
+}
+
+/// Inputs giving the 2D value of an action, like moving
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AxisBinding {
+    Keys {
+        up: KeyCode,
+        down: KeyCode,
+        left: KeyCode,
+        right: KeyCode,
+    },
+    Gamepad {
+        x: GamepadAxisType,
+        y: GamepadAxisType,
+    },
+}
+
+impl AxisBinding {
+    /// The WASD keys
+    pub const WASD: Self = Self::Keys {
+        up: KeyCode::W,
+        down: KeyCode::S,
+        left: KeyCode::A,
+        right: KeyCode::D,
+    };
+
+    /// The arrow keys
+    pub const ARROWS: Self = Self::Keys {
+        up: KeyCode::Up,
+        down: KeyCode::Down,
+        left: KeyCode::Left,
+        right: KeyCode::Right,
+    };
+
+    /// The left stick of the gamepads
+    pub const LEFT_STICK: Self = Self::Gamepad {
+        x: GamepadAxisType::LeftStickX,
+        y: GamepadAxisType::LeftStickY,
+    };
+}
+
+/// A resource binding the actions of type `A` to inputs, which can be
+/// changed at runtime
+///
+/// It can be loaded from a TOML file:
+///
+/// ```toml
+/// [buttons]
+/// Jump = [{ Key = "Space" }, { Gamepad = "South" }]
+///
+/// [axes]
+/// Move = [
+///     { Keys = { up = "W", down = "S", left = "A", right = "D" } },
+///     { Gamepad = { x = "LeftStickX", y = "LeftStickY" } },
+/// ]
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "A: Actionlike")]
+pub struct InputMap<A> {
+    #[serde(default)]
+    buttons: HashMap<A, Vec<Binding>>,
+    #[serde(default)]
+    axes: HashMap<A, Vec<AxisBinding>>,
+}
+
+impl<A> InputMap<A>
+where
+    A: Actionlike,
+{
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            buttons: HashMap::new(),
+            axes: HashMap::new(),
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Will return an error if the file can't be read or isn't a valid
+    /// input map
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, InputMapError> {
+        Self::from_toml(&fs::read_to_string(path)?)
+    }
+
+    /// # Errors
+    ///
+    /// Will return an error if the string isn't a valid input map
+    pub fn from_toml(input_map: &str) -> Result<Self, InputMapError> {
+        Ok(toml::from_str(input_map)?)
+    }
+
+    /// # Panics
+    ///
+    /// Will panic if an action can't be serialized as a TOML key, like a
+    /// struct
+    #[must_use]
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("Input map can't be serialized")
+    }
+
+    #[must_use]
+    pub fn with_binding(mut self, action: A, binding: Binding) -> Self {
+        self.bind(action, binding);
+        self
+    }
+
+    #[must_use]
+    pub fn with_axis_binding(mut self, action: A, binding: AxisBinding) -> Self {
+        self.bind_axis(action, binding);
+        self
+    }
+
+    pub fn bind(&mut self, action: A, binding: Binding) {
+        let bindings = self.buttons.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn bind_axis(&mut self, action: A, binding: AxisBinding) {
+        let bindings = self.axes.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind(&mut self, action: A, binding: Binding) {
+        if let Some(bindings) = self.buttons.get_mut(&action) {
+            bindings.retain(|&bound| bound != binding);
+        }
+    }
+
+    pub fn unbind_axis(&mut self, action: A, binding: AxisBinding) {
+        if let Some(bindings) = self.axes.get_mut(&action) {
+            bindings.retain(|&bound| bound != binding);
+        }
+    }
+
+    /// Removes all the bindings of the action, before binding it to other
+    /// inputs
+    pub fn clear_bindings(&mut self, action: A) {
+        self.buttons.remove(&action);
+        self.axes.remove(&action);
+    }
+
+    #[must_use]
+    pub fn bindings(&self, action: A) -> &[Binding] {
+        self.buttons.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    #[must_use]
+    pub fn axis_bindings(&self, action: A) -> &[AxisBinding] {
+        self.axes.get(&action).map_or(&[], Vec::as_slice)
+    }
+}
+
+impl<A> Default for InputMap<A>
+where
+    A: Actionlike,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A> PartialEq for InputMap<A>
+where
+    A: Actionlike,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.buttons == other.buttons && self.axes == other.axes
+    }
+}
+
+impl<A> Eq for InputMap<A> where A: Actionlike {}
+
+#[derive(Debug)]
+pub enum InputMapError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+}
+
+impl fmt::Display for InputMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "Input map can't be read: {error}"),
+            Self::Toml(error) => write!(f, "Input map is invalid: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for InputMapError {}
+
+impl From<io::Error> for InputMapError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<toml::de::Error> for InputMapError {
+    fn from(error: toml::de::Error) -> Self {
+        Self::Toml(error)
+    }
+}
+
+/// A resource telling which actions of type `A` are triggered, updated from
+/// the [`InputMap<A>`] at the start of each frame
+///
+/// ```
+/// # use butter::{ecs::resource::Res, input::action::ActionState};
+/// # use serde::{Deserialize, Serialize};
+/// # #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// # enum Action {
+/// #     Jump,
+/// #     Move,
+/// # }
+/// fn control(actions: Res<ActionState<Action>>) {
+///     if actions.just_pressed(Action::Jump) {
+///         println!("Jump!");
+///     }
+///     let direction = actions.axis(Action::Move);
+///     println!("Moving towards ({}, {})", direction.x, direction.y);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ActionState<A> {
+    buttons: Input<A>,
+    axes: HashMap<A, Vector2<f32>>,
+}
+
+impl<A> ActionState<A>
+where
+    A: Actionlike,
+{
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            buttons: Input::new(),
+            axes: HashMap::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn pressed(&self, action: A) -> bool {
+        self.buttons.pressed(action)
+    }
+
+    #[must_use]
+    pub fn just_pressed(&self, action: A) -> bool {
+        self.buttons.just_pressed(action)
+    }
+
+    #[must_use]
+    pub fn just_released(&self, action: A) -> bool {
+        self.buttons.just_released(action)
+    }
+
+    /// The direction of the action, whose norm is at most 1, with y pointing
+    /// up
+    pub fn axis(&self, action: A) -> Vector2<f32> {
+        self.axes.get(&action).copied().unwrap_or_default()
+    }
+}
+
+impl<A> Default for ActionState<A>
+where
+    A: Actionlike,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Registers the [`InputMap<A>`] and [`ActionState<A>`] resources
+///
+/// It must be added after the [`InputPlugin`](super::InputPlugin) and the
+/// [`GamepadPlugin`](super::GamepadPlugin), so that the actions are updated
+/// from the input of the current frame.
+pub struct ActionPlugin<A> {
+    input_map: InputMap<A>,
+}
+
+impl<A> ActionPlugin<A>
+where
+    A: Actionlike,
+{
+    #[must_use]
+    pub fn new(input_map: InputMap<A>) -> Self {
+        Self { input_map }
+    }
+}
+
+impl<A> Plugin for ActionPlugin<A>
+where
+    A: Actionlike,
+{
+    fn build(&self, builder: &mut ButterEngineBuilder) {
+        // The inputs stay released unless their plugins are registered too
+        builder
+            .with_default_resource::<Input<KeyCode>>()
+            .with_default_resource::<Input<MouseButton>>()
+            .with_default_resource::<Gamepads>()
+            .with_default_resource::<Input<GamepadButton>>()
+            .with_default_resource::<Axis<GamepadAxis>>()
+            .with_resource(self.input_map.clone())
+            .with_resource(ActionState::<A>::new())
+            .with_pre_update_system(action_state_system::<A>);
+    }
+}
+
+/// The inputs that actions can be bound to
+struct Inputs<'a> {
+    keys: &'a Input<KeyCode>,
+    mouse_buttons: &'a Input<MouseButton>,
+    gamepads: &'a Gamepads,
+    gamepad_buttons: &'a Input<GamepadButton>,
+    gamepad_axes: &'a Axis<GamepadAxis>,
+}
+
+impl Inputs<'_> {
+    fn pressed(&self, binding: Binding) -> bool {
+        match binding {
+            Binding::Key(key_code) => self.keys.pressed(key_code),
+            Binding::Mouse(button) => self.mouse_buttons.pressed(button),
+            Binding::Gamepad(button_type) => self.gamepads.iter().any(|gamepad| {
+                self.gamepad_buttons
+                    .pressed(GamepadButton::new(gamepad, button_type))
+            }),
+        }
+    }
+
+    fn axis(&self, binding: AxisBinding) -> Vector2<f32> {
+        match binding {
+            AxisBinding::Keys {
+                up,
+                down,
+                left,
+                right,
+            } => {
+                let value = |key_code| {
+                    if self.keys.pressed(key_code) {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                };
+                Vector2::new(value(right) - value(left), value(up) - value(down))
+            }
+            AxisBinding::Gamepad { x, y } => {
+                let value = |gamepad, axis_type| {
+                    self.gamepad_axes
+                        .get(GamepadAxis::new(gamepad, axis_type))
+                        .unwrap_or(0.0)
+                };
+                self.gamepads
+                    .iter()
+                    .map(|gamepad| Vector2::new(value(gamepad, x), value(gamepad, y)))
+                    .fold(Vector2::default(), |sum, value| sum + value)
+            }
+        }
+    }
+}
+
+/// Updates the [`ActionState<A>`] from the bound inputs
+#[allow(clippy::needless_pass_by_value)]
+pub fn action_state_system<A>(
+    input_map: Res<InputMap<A>>,
+    mut action_state: ResMut<ActionState<A>>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+) where
+    A: Actionlike,
+{
+    let inputs = Inputs {
+        keys: &keys,
+        mouse_buttons: &mouse_buttons,
+        gamepads: &gamepads,
+        gamepad_buttons: &gamepad_buttons,
+        gamepad_axes: &gamepad_axes,
+    };
+
+    action_state.buttons.clear();
+    for (&action, bindings) in &input_map.buttons {
+        if bindings.iter().any(|&binding| inputs.pressed(binding)) {
+            action_state.buttons.press(action);
+        } else {
+            action_state.buttons.release(action);
+        }
+    }
+    // Unbound actions are released instead of staying stuck
+    let unbound: Vec<_> = action_state
+        .buttons
+        .get_pressed()
+        .filter(|action| !input_map.buttons.contains_key(action))
+        .copied()
+        .collect();
+    for action in unbound {
+        action_state.buttons.release(action);
+    }
+
+    action_state.axes.clear();
+    for (&action, bindings) in &input_map.axes {
+        let mut axis = bindings
+            .iter()
+            .map(|&binding| inputs.axis(binding))
+            .fold(Vector2::default(), |sum, value| sum + value);
+        if axis.norm() > 1.0 {
+            axis.normalize();
+        }
+        action_state.axes.insert(action, axis);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{
+        ButtonState, Gamepad, GamepadEvent, GamepadEventType, GamepadPlugin, InputPlugin,
+        KeyboardInput,
+    };
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    enum Action {
+        Jump,
+        Move,
+    }
+
+    fn key(key_code: KeyCode, state: ButtonState) -> KeyboardInput {
+        KeyboardInput {
+            key_code: Some(key_code),
+            scan_code: 0,
+            state,
+        }
+    }
+
+    #[test]
+    fn input_map_from_toml() {
+        let input_map = InputMap::<Action>::from_toml(
+            r#"
+            [buttons]
+            Jump = [{ Key = "Space" }, { Gamepad = "South" }]
+
+            [axes]
+            Move = [
+                { Keys = { up = "W", down = "S", left = "A", right = "D" } },
+                { Gamepad = { x = "LeftStickX", y = "LeftStickY" } },
+            ]
+            "#,
+        )
+        .unwrap();
+
+        let expected = InputMap::new()
+            .with_binding(Action::Jump, Binding::Key(KeyCode::Space))
+            .with_binding(Action::Jump, Binding::Gamepad(GamepadButtonType::South))
+            .with_axis_binding(Action::Move, AxisBinding::WASD)
+            .with_axis_binding(Action::Move, AxisBinding::LEFT_STICK);
+        assert_eq!(input_map, expected);
+        assert_eq!(InputMap::from_toml(&expected.to_toml()).unwrap(), expected);
+        assert!(InputMap::<Action>::from_toml("[buttons]\nFly = []").is_err());
+    }
+
+    #[test]
+    fn actions_from_input() {
+        let input_map = InputMap::new()
+            .with_binding(Action::Jump, Binding::Key(KeyCode::Space))
+            .with_axis_binding(Action::Move, AxisBinding::WASD)
+            .with_axis_binding(Action::Move, AxisBinding::LEFT_STICK);
+        // Registered before the input plugins, which doesn't delay the actions
+        let mut engine = ButterEngineBuilder::new()
+            .with_plugin(ActionPlugin::new(input_map))
+            .with_plugin(InputPlugin)
+            .with_plugin(GamepadPlugin::new())
+            .build();
+        engine.init();
+
+        engine.send_event(key(KeyCode::Space, ButtonState::Pressed));
+        engine.send_event(key(KeyCode::D, ButtonState::Pressed));
+        engine.send_event(GamepadEvent::new(Gamepad(0), GamepadEventType::Connected));
+        engine.send_event(GamepadEvent::new(
+            Gamepad(0),
+            GamepadEventType::AxisChanged(GamepadAxisType::LeftStickY, 1.0),
+        ));
+        engine.update();
+
+        let actions = engine.ecs().resource::<ActionState<Action>>().unwrap();
+        assert!(actions.just_pressed(Action::Jump));
+        let direction = actions.axis(Action::Move);
+        assert!((direction.x - direction.y).abs() < f32::EPSILON);
+        assert!((direction.norm() - 1.0).abs() < 1e-6);
+
+        // Rebinding jump releases it, as space isn't bound anymore
+        let input_map = engine.ecs_mut().resource_mut::<InputMap<Action>>().unwrap();
+        input_map.clear_bindings(Action::Jump);
+        input_map.bind(Action::Jump, Binding::Key(KeyCode::Up));
+        engine.update();
+
+        let actions = engine.ecs().resource::<ActionState<Action>>().unwrap();
+        assert!(actions.just_released(Action::Jump));
+        engine.send_event(key(KeyCode::Up, ButtonState::Pressed));
+        engine.update();
+        let actions = engine.ecs().resource::<ActionState<Action>>().unwrap();
+        assert!(actions.just_pressed(Action::Jump));
+    }
+
+    #[test]
+    fn actions_without_input_plugins() {
+        let input_map = InputMap::new().with_binding(Action::Jump, Binding::Key(KeyCode::Space));
+        let mut engine = ButterEngineBuilder::new()
+            .with_plugin(ActionPlugin::new(input_map))
+            .build();
+        engine.init();
+        engine.update();
+
+        let actions = engine.ecs().resource::<ActionState<Action>>().unwrap();
+        assert!(!actions.pressed(Action::Jump));
+    }
+}
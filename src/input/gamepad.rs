@@ -0,0 +1,620 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ecs::{
+        event::{EventReader, EventWriter},
+        resource::{NonSendMut, Res, ResMut},
+    },
+    plugin::Plugin,
+    ButterEngineBuilder,
+};
+
+use super::Input;
+
+/// A gamepad, identified by the backend
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Gamepad(pub usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadButtonType {
+    /// The bottom action button, A on Xbox controllers
+    South,
+    East,
+    North,
+    West,
+    C,
+    Z,
+    LeftTrigger,
+    LeftTrigger2,
+    RightTrigger,
+    RightTrigger2,
+    Select,
+    Start,
+    Mode,
+    LeftThumb,
+    RightThumb,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+/// A button of a gamepad, whose state is given by the `Input<GamepadButton>`
+/// resource
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GamepadButton {
+    pub gamepad: Gamepad,
+    pub button_type: GamepadButtonType,
+}
+
+impl GamepadButton {
+    #[must_use]
+    pub fn new(gamepad: Gamepad, button_type: GamepadButtonType) -> Self {
+        Self {
+            gamepad,
+            button_type,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadAxisType {
+    LeftStickX,
+    LeftStickY,
+    LeftZ,
+    RightStickX,
+    RightStickY,
+    RightZ,
+}
+
+/// An axis of a gamepad, whose value is given by the `Axis<GamepadAxis>`
+/// resource
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GamepadAxis {
+    pub gamepad: Gamepad,
+    pub axis_type: GamepadAxisType,
+}
+
+impl GamepadAxis {
+    #[must_use]
+    pub fn new(gamepad: Gamepad, axis_type: GamepadAxisType) -> Self {
+        Self { gamepad, axis_type }
+    }
+}
+
+/// A resource giving the value of analog inputs of type `T`, between -1
+/// and 1
+#[derive(Clone, Debug)]
+pub struct Axis<T> {
+    values: HashMap<T, f32>,
+}
+
+impl<T> Axis<T>
+where
+    T: Copy + Eq + std::hash::Hash,
+{
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            values: HashMap::new(),
+        }
+    }
+
+    /// `None` if the axis has never moved or its gamepad is disconnected
+    #[must_use]
+    pub fn get(&self, axis: T) -> Option<f32> {
+        self.values.get(&axis).copied()
+    }
+
+    pub fn set(&mut self, axis: T, value: f32) {
+        self.values.insert(axis, value);
+    }
+
+    pub fn remove(&mut self, axis: T) -> Option<f32> {
+        self.values.remove(&axis)
+    }
+}
+
+impl<T> Default for Axis<T>
+where
+    T: Copy + Eq + std::hash::Hash,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A resource listing the connected gamepads
+#[derive(Clone, Debug, Default)]
+pub struct Gamepads {
+    gamepads: HashSet<Gamepad>,
+}
+
+impl Gamepads {
+    #[must_use]
+    pub fn contains(&self, gamepad: Gamepad) -> bool {
+        self.gamepads.contains(&gamepad)
+    }
+
+    /// Iterates over the connected gamepads in increasing id order
+    pub fn iter(&self) -> impl Iterator<Item = Gamepad> {
+        let mut gamepads: Vec<_> = self.gamepads.iter().copied().collect();
+        gamepads.sort_unstable();
+        gamepads.into_iter()
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.gamepads.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.gamepads.is_empty()
+    }
+}
+
+/// How the raw values of an axis are filtered
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AxisSettings {
+    dead_zone: f32,
+    live_zone: f32,
+}
+
+impl AxisSettings {
+    /// The values closer to 0 than the dead zone are ignored, as sticks
+    /// rarely rest exactly at the center, and the ones further than the live
+    /// zone are considered as -1 or 1
+    ///
+    /// # Panics
+    ///
+    /// Will panic unless `0 <= dead_zone < live_zone <= 1`
+    #[must_use]
+    pub fn new(dead_zone: f32, live_zone: f32) -> Self {
+        assert!(
+            (0.0..live_zone).contains(&dead_zone) && live_zone <= 1.0,
+            "The dead zone {dead_zone} and live zone {live_zone} must verify 0 <= dead zone < live zone <= 1"
+        );
+        Self {
+            dead_zone,
+            live_zone,
+        }
+    }
+
+    #[must_use]
+    pub fn dead_zone(&self) -> f32 {
+        self.dead_zone
+    }
+
+    #[must_use]
+    pub fn live_zone(&self) -> f32 {
+        self.live_zone
+    }
+
+    /// Maps the values between the dead zone and the live zone to `0..=1`,
+    /// keeping their sign
+    #[must_use]
+    pub fn filter(&self, value: f32) -> f32 {
+        let magnitude = value.abs();
+        if magnitude <= self.dead_zone {
+            return 0.0;
+        }
+
+        let scaled = (magnitude - self.dead_zone) / (self.live_zone - self.dead_zone);
+        scaled.min(1.0).copysign(value)
+    }
+}
+
+impl Default for AxisSettings {
+    fn default() -> Self {
+        Self::new(0.1, 0.95)
+    }
+}
+
+/// When an analog button, like a trigger, counts as pressed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ButtonSettings {
+    pub press_threshold: f32,
+    /// Lower than the press threshold, so that a trigger held halfway
+    /// doesn't flicker
+    pub release_threshold: f32,
+}
+
+impl Default for ButtonSettings {
+    fn default() -> Self {
+        Self {
+            press_threshold: 0.75,
+            release_threshold: 0.65,
+        }
+    }
+}
+
+/// A resource with the dead zones and thresholds of the gamepads, which can
+/// be set per axis or button
+#[derive(Clone, Debug, Default)]
+pub struct GamepadSettings {
+    pub default_axis_settings: AxisSettings,
+    pub default_button_settings: ButtonSettings,
+    pub axis_settings: HashMap<GamepadAxis, AxisSettings>,
+    pub button_settings: HashMap<GamepadButton, ButtonSettings>,
+}
+
+impl GamepadSettings {
+    #[must_use]
+    pub fn axis_settings(&self, axis: GamepadAxis) -> &AxisSettings {
+        self.axis_settings
+            .get(&axis)
+            .unwrap_or(&self.default_axis_settings)
+    }
+
+    #[must_use]
+    pub fn button_settings(&self, button: GamepadButton) -> &ButtonSettings {
+        self.button_settings
+            .get(&button)
+            .unwrap_or(&self.default_button_settings)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GamepadEventType {
+    Connected,
+    Disconnected,
+    /// The raw value of the button, between 0 and 1
+    ButtonChanged(GamepadButtonType, f32),
+    /// The raw value of the axis, between -1 and 1
+    AxisChanged(GamepadAxisType, f32),
+}
+
+/// Event sent by the gamepad backend, from which the gamepad resources are
+/// updated
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GamepadEvent {
+    pub gamepad: Gamepad,
+    pub event_type: GamepadEventType,
+}
+
+impl GamepadEvent {
+    #[must_use]
+    pub fn new(gamepad: Gamepad, event_type: GamepadEventType) -> Self {
+        Self {
+            gamepad,
+            event_type,
+        }
+    }
+}
+
+/// A source of [`GamepadEvent`]s, polled at the start of each frame
+pub trait GamepadBackend: 'static {
+    fn poll(&mut self, send: &mut dyn FnMut(GamepadEvent));
+}
+
+/// A backend whose events are given by hand, to test the systems using the
+/// gamepads
+///
+/// Its clones share the same events.
+#[derive(Clone, Debug, Default)]
+pub struct GamepadStub {
+    events: Arc<Mutex<Vec<GamepadEvent>>>,
+}
+
+impl GamepadStub {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # Panics
+    ///
+    /// Will panic if another thread panicked while sending an event
+    pub fn send(&self, gamepad: Gamepad, event_type: GamepadEventType) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(GamepadEvent::new(gamepad, event_type));
+    }
+}
+
+impl GamepadBackend for GamepadStub {
+    fn poll(&mut self, send: &mut dyn FnMut(GamepadEvent)) {
+        for event in self.events.lock().unwrap().drain(..) {
+            send(event);
+        }
+    }
+}
+
+#[cfg(feature = "gilrs")]
+pub use gilrs_backend::GilrsBackend;
+
+#[cfg(feature = "gilrs")]
+mod gilrs_backend {
+    use gilrs::{Axis, Button, EventType, Gilrs};
+
+    use super::{
+        Gamepad, GamepadAxisType, GamepadBackend, GamepadButtonType, GamepadEvent, GamepadEventType,
+    };
+
+    /// Reads the gamepads through [gilrs](https://docs.rs/gilrs)
+    pub struct GilrsBackend {
+        gilrs: Gilrs,
+    }
+
+    impl GilrsBackend {
+        /// # Errors
+        ///
+        /// Will return an error if the platform isn't supported
+        pub fn new() -> Result<Self, Box<gilrs::Error>> {
+            Ok(Self {
+                gilrs: Gilrs::new().map_err(Box::new)?,
+            })
+        }
+    }
+
+    impl GamepadBackend for GilrsBackend {
+        fn poll(&mut self, send: &mut dyn FnMut(GamepadEvent)) {
+            while let Some(gilrs::Event { id, event, .. }) = self.gilrs.next_event() {
+                let event_type = match event {
+                    EventType::Connected => GamepadEventType::Connected,
+                    EventType::Disconnected => GamepadEventType::Disconnected,
+                    EventType::ButtonChanged(button, value, _) => match button_type(button) {
+                        Some(button_type) => GamepadEventType::ButtonChanged(button_type, value),
+                        None => continue,
+                    },
+                    EventType::AxisChanged(axis, value, _) => match axis_type(axis) {
+                        Some(axis_type) => GamepadEventType::AxisChanged(axis_type, value),
+                        None => continue,
+                    },
+                    _ => continue,
+                };
+                send(GamepadEvent::new(Gamepad(usize::from(id)), event_type));
+            }
+        }
+    }
+
+    fn button_type(button: Button) -> Option<GamepadButtonType> {
+        Some(match button {
+            Button::South => GamepadButtonType::South,
+            Button::East => GamepadButtonType::East,
+            Button::North => GamepadButtonType::North,
+            Button::West => GamepadButtonType::West,
+            Button::C => GamepadButtonType::C,
+            Button::Z => GamepadButtonType::Z,
+            Button::LeftTrigger => GamepadButtonType::LeftTrigger,
+            Button::LeftTrigger2 => GamepadButtonType::LeftTrigger2,
+            Button::RightTrigger => GamepadButtonType::RightTrigger,
+            Button::RightTrigger2 => GamepadButtonType::RightTrigger2,
+            Button::Select => GamepadButtonType::Select,
+            Button::Start => GamepadButtonType::Start,
+            Button::Mode => GamepadButtonType::Mode,
+            Button::LeftThumb => GamepadButtonType::LeftThumb,
+            Button::RightThumb => GamepadButtonType::RightThumb,
+            Button::DPadUp => GamepadButtonType::DPadUp,
+            Button::DPadDown => GamepadButtonType::DPadDown,
+            Button::DPadLeft => GamepadButtonType::DPadLeft,
+            Button::DPadRight => GamepadButtonType::DPadRight,
+            Button::Unknown => return None,
+        })
+    }
+
+    fn axis_type(axis: Axis) -> Option<GamepadAxisType> {
+        Some(match axis {
+            Axis::LeftStickX => GamepadAxisType::LeftStickX,
+            Axis::LeftStickY => GamepadAxisType::LeftStickY,
+            Axis::LeftZ => GamepadAxisType::LeftZ,
+            Axis::RightStickX => GamepadAxisType::RightStickX,
+            Axis::RightStickY => GamepadAxisType::RightStickY,
+            Axis::RightZ => GamepadAxisType::RightZ,
+            Axis::DPadX | Axis::DPadY | Axis::Unknown => return None,
+        })
+    }
+}
+
+type BackendFactory = Box<dyn Fn() -> Option<Box<dyn GamepadBackend>>>;
+
+/// Registers the gamepad resources and events, fed by a backend
+///
+/// The default backend is gilrs when the `gilrs` feature is enabled, and
+/// none otherwise, the [`GamepadEvent`]s then having to be sent by hand.
+pub struct GamepadPlugin {
+    backend: Option<BackendFactory>,
+}
+
+impl GamepadPlugin {
+    /// A plugin without backend
+    #[must_use]
+    pub fn new() -> Self {
+        Self { backend: None }
+    }
+
+    /// Sets the function creating the backend when the plugin is built, which
+    /// can fail, like when the platform isn't supported
+    #[must_use]
+    pub fn with_backend<F, B>(mut self, backend: F) -> Self
+    where
+        F: Fn() -> Option<B> + 'static,
+        B: GamepadBackend,
+    {
+        self.backend = Some(Box::new(move || {
+            backend().map(|backend| Box::new(backend) as Box<dyn GamepadBackend>)
+        }));
+        self
+    }
+}
+
+impl Default for GamepadPlugin {
+    fn default() -> Self {
+        #[cfg(feature = "gilrs")]
+        return Self::new().with_backend(|| GilrsBackend::new().ok());
+        #[cfg(not(feature = "gilrs"))]
+        return Self::new();
+    }
+}
+
+impl Plugin for GamepadPlugin {
+    fn build(&self, builder: &mut ButterEngineBuilder) {
+        builder
+            .with_resource(Gamepads::default())
+            .with_resource(GamepadSettings::default())
+            .with_resource(Input::<GamepadButton>::new())
+            .with_resource(Axis::<GamepadAxis>::new())
+            .with_event::<GamepadEvent>();
+
+        if let Some(backend) = self.backend.as_ref().and_then(|backend| backend()) {
+            builder
+                .with_non_send_resource(backend)
+                .with_first_system(poll_gamepad_backend);
+        }
+        builder.with_first_system(gamepad_event_system);
+    }
+}
+
+/// Sends the events of the backend
+fn poll_gamepad_backend(
+    mut backend: NonSendMut<Box<dyn GamepadBackend>>,
+    mut gamepad_events: EventWriter<GamepadEvent>,
+) {
+    backend.poll(&mut |event| gamepad_events.send(event));
+}
+
+/// Updates the gamepad resources from the [`GamepadEvent`]s
+#[allow(clippy::needless_pass_by_value)]
+pub fn gamepad_event_system(
+    mut gamepads: ResMut<Gamepads>,
+    mut buttons: ResMut<Input<GamepadButton>>,
+    mut axes: ResMut<Axis<GamepadAxis>>,
+    settings: Res<GamepadSettings>,
+    mut gamepad_events: EventReader<GamepadEvent>,
+) {
+    buttons.clear();
+    for &GamepadEvent {
+        gamepad,
+        event_type,
+    } in gamepad_events.read()
+    {
+        match event_type {
+            GamepadEventType::Connected => {
+                gamepads.gamepads.insert(gamepad);
+            }
+            GamepadEventType::Disconnected => {
+                gamepads.gamepads.remove(&gamepad);
+                let pressed: Vec<_> = buttons
+                    .get_pressed()
+                    .filter(|button| button.gamepad == gamepad)
+                    .copied()
+                    .collect();
+                for button in pressed {
+                    buttons.release(button);
+                }
+                axes.values.retain(|axis, _| axis.gamepad != gamepad);
+            }
+            GamepadEventType::ButtonChanged(button_type, value) => {
+                let button = GamepadButton::new(gamepad, button_type);
+                let button_settings = settings.button_settings(button);
+                if value >= button_settings.press_threshold {
+                    buttons.press(button);
+                } else if value <= button_settings.release_threshold {
+                    buttons.release(button);
+                }
+            }
+            GamepadEventType::AxisChanged(axis_type, value) => {
+                let gamepad_axis = GamepadAxis::new(gamepad, axis_type);
+                let axis_settings = settings.axis_settings(gamepad_axis);
+                axes.set(gamepad_axis, axis_settings.filter(value));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn axis_dead_zones() {
+        let settings = AxisSettings::default();
+        assert!(settings.filter(0.05).abs() < f32::EPSILON);
+        assert!((settings.filter(-0.95) + 1.0).abs() < f32::EPSILON);
+        assert!((settings.filter(1.0) - 1.0).abs() < f32::EPSILON);
+        assert!((settings.filter(0.525) - 0.5).abs() < 1e-5);
+
+        let settings = AxisSettings::new(0.0, 0.5);
+        assert!((settings.filter(-0.25) + 0.5).abs() < f32::EPSILON);
+        assert!((settings.filter(0.75) - 1.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    #[should_panic(expected = "must verify 0 <= dead zone < live zone <= 1")]
+    fn axis_zones_overlapping() {
+        let _ = AxisSettings::new(0.5, 0.5);
+    }
+
+    #[test]
+    fn gamepads_from_stub() {
+        let stub = GamepadStub::new();
+        let backend = stub.clone();
+        let mut engine = ButterEngineBuilder::new()
+            .with_plugin(GamepadPlugin::new().with_backend(move || Some(backend.clone())))
+            .build();
+        engine.init();
+
+        let player_one = Gamepad(0);
+        let jump = GamepadButton::new(player_one, GamepadButtonType::South);
+        let stick = GamepadAxis::new(player_one, GamepadAxisType::LeftStickX);
+        stub.send(player_one, GamepadEventType::Connected);
+        stub.send(
+            player_one,
+            GamepadEventType::ButtonChanged(GamepadButtonType::South, 1.0),
+        );
+        stub.send(
+            player_one,
+            GamepadEventType::AxisChanged(GamepadAxisType::LeftStickX, 0.05),
+        );
+        engine.update();
+
+        let ecs = engine.ecs();
+        assert_eq!(
+            ecs.resource::<Gamepads>()
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            [player_one]
+        );
+        assert!(ecs
+            .resource::<Input<GamepadButton>>()
+            .unwrap()
+            .just_pressed(jump));
+        assert_eq!(
+            ecs.resource::<Axis<GamepadAxis>>().unwrap().get(stick),
+            Some(0.0)
+        );
+
+        // Between the thresholds, the button stays pressed
+        stub.send(
+            player_one,
+            GamepadEventType::ButtonChanged(GamepadButtonType::South, 0.7),
+        );
+        engine.update();
+        assert!(engine
+            .ecs()
+            .resource::<Input<GamepadButton>>()
+            .unwrap()
+            .pressed(jump));
+
+        stub.send(player_one, GamepadEventType::Disconnected);
+        engine.update();
+        let ecs = engine.ecs();
+        assert!(ecs.resource::<Gamepads>().unwrap().is_empty());
+        assert!(ecs
+            .resource::<Input<GamepadButton>>()
+            .unwrap()
+            .just_released(jump));
+        assert_eq!(
+            ecs.resource::<Axis<GamepadAxis>>().unwrap().get(stick),
+            None
+        );
+    }
+}
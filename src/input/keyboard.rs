@@ -0,0 +1,47 @@
+use crate::{
+    ecs::{event::EventReader, resource::ResMut},
+    window::WindowFocused,
+};
+
+use super::{ButtonState, Input};
+
+pub use winit::event::VirtualKeyCode as KeyCode;
+
+/// Event sent when a key is pressed or released
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyboardInput {
+    /// The key, if it is known, depending on the layout of the keyboard
+    pub key_code: Option<KeyCode>,
+    /// The physical key, independent of the layout
+    pub scan_code: u32,
+    pub state: ButtonState,
+}
+
+/// Event sent for each character typed, for text entry
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReceivedCharacter(pub char);
+
+/// Updates the [`Input<KeyCode>`] from the [`KeyboardInput`] events,
+/// releasing every key when a window loses the focus since their release
+/// won't be received
+pub fn keyboard_input_system(
+    mut keys: ResMut<Input<KeyCode>>,
+    mut keyboard_inputs: EventReader<KeyboardInput>,
+    mut focus_changes: EventReader<WindowFocused>,
+) {
+    keys.clear();
+    for keyboard_input in keyboard_inputs.read() {
+        if let Some(key_code) = keyboard_input.key_code {
+            match keyboard_input.state {
+                ButtonState::Pressed => keys.press(key_code),
+                ButtonState::Released => keys.release(key_code),
+            }
+        }
+    }
+    if focus_changes
+        .read()
+        .any(|focus_change| !focus_change.focused)
+    {
+        keys.release_all();
+    }
+}
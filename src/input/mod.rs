@@ -0,0 +1,326 @@
+use std::{collections::HashSet, hash::Hash};
+
+use crate::{plugin::Plugin, ButterEngineBuilder};
+
+pub mod action;
+pub mod gamepad;
+pub mod keyboard;
+pub mod mouse;
+
+pub use action::{ActionPlugin, ActionState, Actionlike, AxisBinding, Binding, InputMap};
+pub use gamepad::{
+    Axis, Gamepad, GamepadAxis, GamepadAxisType, GamepadButton, GamepadButtonType, GamepadEvent,
+    GamepadEventType, GamepadPlugin, GamepadSettings, Gamepads,
+};
+pub use keyboard::{KeyCode, KeyboardInput, ReceivedCharacter};
+pub use mouse::{
+    Cursor, CursorLeft, CursorMoved, MouseButton, MouseButtonInput, MouseMotion, MouseWheel,
+};
+
+/// A resource telling which buttons of type `T` are pressed, like the keys of
+/// the keyboard
+///
+/// ```
+/// # use butter::{ecs::resource::Res, input::{Input, KeyCode}};
+/// fn jump(keys: Res<Input<KeyCode>>) {
+///     if keys.just_pressed(KeyCode::Space) {
+///         println!("Jump!");
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Input<T> {
+    pressed: HashSet<T>,
+    just_pressed: HashSet<T>,
+    just_released: HashSet<T>,
+}
+
+impl<T> Input<T>
+where
+    T: Copy + Eq + Hash,
+{
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            pressed: HashSet::new(),
+            just_pressed: HashSet::new(),
+            just_released: HashSet::new(),
+        }
+    }
+
+    pub fn press(&mut self, button: T) {
+        if self.pressed.insert(button) {
+            self.just_pressed.insert(button);
+        }
+    }
+
+    pub fn release(&mut self, button: T) {
+        if self.pressed.remove(&button) {
+            self.just_released.insert(button);
+        }
+    }
+
+    pub fn release_all(&mut self) {
+        self.just_released.extend(self.pressed.drain());
+    }
+
+    #[must_use]
+    pub fn pressed(&self, button: T) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    #[must_use]
+    pub fn any_pressed(&self, buttons: impl IntoIterator<Item = T>) -> bool {
+        buttons.into_iter().any(|button| self.pressed(button))
+    }
+
+    /// Whether the button has been pressed since the last frame
+    #[must_use]
+    pub fn just_pressed(&self, button: T) -> bool {
+        self.just_pressed.contains(&button)
+    }
+
+    /// Whether the button has been released since the last frame
+    #[must_use]
+    pub fn just_released(&self, button: T) -> bool {
+        self.just_released.contains(&button)
+    }
+
+    pub fn get_pressed(&self) -> impl Iterator<Item = &T> {
+        self.pressed.iter()
+    }
+
+    pub fn get_just_pressed(&self) -> impl Iterator<Item = &T> {
+        self.just_pressed.iter()
+    }
+
+    pub fn get_just_released(&self) -> impl Iterator<Item = &T> {
+        self.just_released.iter()
+    }
+
+    /// Starts a new frame, forgetting which buttons were just pressed or
+    /// released
+    pub fn clear(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+    }
+}
+
+impl<T> Default for Input<T>
+where
+    T: Copy + Eq + Hash,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ButtonState {
+    Pressed,
+    Released,
+}
+
+/// Registers the keyboard and mouse resources and events, which the runner
+/// feeds from the window events
+pub struct InputPlugin;
+
+impl Plugin for InputPlugin {
+    fn build(&self, builder: &mut ButterEngineBuilder) {
+        builder
+            .with_resource(Input::<KeyCode>::new())
+            .with_resource(Input::<MouseButton>::new())
+            .with_resource(Cursor::default())
+            .with_event::<KeyboardInput>()
+            .with_event::<ReceivedCharacter>()
+            .with_event::<MouseButtonInput>()
+            .with_event::<CursorMoved>()
+            .with_event::<CursorLeft>()
+            .with_event::<MouseWheel>()
+            .with_event::<MouseMotion>()
+            .with_first_system(keyboard::keyboard_input_system)
+            .with_first_system(mouse::mouse_input_system);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ecs::{
+            event::Events,
+            resource::{Res, ResMut},
+        },
+        window::{self, Window, WindowFocused, WindowId},
+    };
+    use butter_math::vector::Vector2;
+
+    #[test]
+    fn input_states() {
+        let mut keys = Input::new();
+        keys.press(KeyCode::A);
+        keys.press(KeyCode::A);
+        assert!(keys.pressed(KeyCode::A));
+        assert!(keys.just_pressed(KeyCode::A));
+
+        keys.clear();
+        assert!(keys.pressed(KeyCode::A));
+        assert!(!keys.just_pressed(KeyCode::A));
+
+        keys.release(KeyCode::A);
+        keys.release(KeyCode::B);
+        assert!(keys.just_released(KeyCode::A));
+        assert!(!keys.just_released(KeyCode::B));
+        assert!(!keys.any_pressed([KeyCode::A, KeyCode::B]));
+    }
+
+    #[test]
+    fn input_from_events() {
+        let mut engine = ButterEngineBuilder::new().with_plugin(InputPlugin).build();
+        engine.init();
+        engine.send_event(KeyboardInput {
+            key_code: Some(KeyCode::Space),
+            scan_code: 57,
+            state: ButtonState::Pressed,
+        });
+        engine.send_event(MouseButtonInput {
+            button: MouseButton::Left,
+            state: ButtonState::Pressed,
+        });
+        engine.move_cursor(WindowId::Primary, Some(Vector2::new(10.0, 20.0)));
+        engine.update();
+
+        let ecs = engine.ecs();
+        assert!(ecs
+            .resource::<Input<KeyCode>>()
+            .unwrap()
+            .just_pressed(KeyCode::Space));
+        assert!(ecs
+            .resource::<Input<MouseButton>>()
+            .unwrap()
+            .pressed(MouseButton::Left));
+        assert_eq!(
+            ecs.resource::<Cursor>().unwrap().position(),
+            Some(Vector2::new(10.0, 20.0))
+        );
+
+        engine.send_event(KeyboardInput {
+            key_code: Some(KeyCode::Space),
+            scan_code: 57,
+            state: ButtonState::Released,
+        });
+        engine.move_cursor(WindowId::Primary, None);
+        engine.update();
+
+        let ecs = engine.ecs();
+        let keys = ecs.resource::<Input<KeyCode>>().unwrap();
+        assert!(!keys.just_pressed(KeyCode::Space));
+        assert!(keys.just_released(KeyCode::Space));
+        assert_eq!(ecs.resource::<Cursor>().unwrap().position(), None);
+    }
+
+    #[test]
+    fn pre_update_systems_see_the_input() {
+        #[derive(Default)]
+        struct Jumped(bool);
+
+        #[allow(clippy::needless_pass_by_value)]
+        fn jump(keys: Res<Input<KeyCode>>, mut jumped: ResMut<Jumped>) {
+            jumped.0 |= keys.just_pressed(KeyCode::Space);
+        }
+
+        let mut engine = ButterEngineBuilder::new()
+            .with_resource(Jumped::default())
+            .with_pre_update_system(jump)
+            .with_plugin(InputPlugin)
+            .build();
+        engine.init();
+        engine.send_event(KeyboardInput {
+            key_code: Some(KeyCode::Space),
+            scan_code: 57,
+            state: ButtonState::Pressed,
+        });
+        engine.update();
+        assert!(engine.ecs().resource::<Jumped>().unwrap().0);
+    }
+
+    #[test]
+    fn cursor_leaving_and_entering_in_one_frame() {
+        let mut engine = ButterEngineBuilder::new().with_plugin(InputPlugin).build();
+        engine.init();
+        engine.move_cursor(WindowId::Primary, Some(Vector2::new(10.0, 20.0)));
+        engine.move_cursor(WindowId::Primary, None);
+        engine.move_cursor(WindowId::Primary, Some(Vector2::new(0.0, 5.0)));
+        engine.update();
+
+        let ecs = engine.ecs();
+        assert_eq!(
+            ecs.resource::<Cursor>().unwrap().position(),
+            Some(Vector2::new(0.0, 5.0))
+        );
+        assert_eq!(ecs.resource::<Events<CursorMoved>>().unwrap().len(), 2);
+        assert_eq!(ecs.resource::<Events<CursorLeft>>().unwrap().len(), 1);
+
+        engine.move_cursor(WindowId::Primary, Some(Vector2::new(1.0, 5.0)));
+        engine.move_cursor(WindowId::Primary, None);
+        engine.update();
+        assert_eq!(engine.ecs().resource::<Cursor>().unwrap().position(), None);
+    }
+
+    #[test]
+    fn cursor_moving_between_windows() {
+        let mut engine = ButterEngineBuilder::new().with_plugin(InputPlugin).build();
+        engine.init();
+        let other_window = WindowId::Entity(
+            engine
+                .ecs_mut()
+                .insert((Window::new(&window::Settings::default()),)),
+        );
+        engine.move_cursor(WindowId::Primary, Some(Vector2::new(10.0, 20.0)));
+        // Entering the other window before leaving the primary one
+        engine.move_cursor(other_window, Some(Vector2::new(0.0, 5.0)));
+        engine.move_cursor(WindowId::Primary, None);
+        engine.update();
+
+        let cursor = engine.ecs().resource::<Cursor>().unwrap();
+        assert_eq!(cursor.window(), Some(other_window));
+        assert_eq!(cursor.position(), Some(Vector2::new(0.0, 5.0)));
+
+        engine.move_cursor(other_window, None);
+        engine.update();
+        let cursor = engine.ecs().resource::<Cursor>().unwrap();
+        assert_eq!(cursor.window(), None);
+        assert_eq!(cursor.position(), None);
+    }
+
+    #[test]
+    fn input_released_on_focus_lost() {
+        let mut engine = ButterEngineBuilder::new().with_plugin(InputPlugin).build();
+        engine.init();
+        engine.send_event(KeyboardInput {
+            key_code: Some(KeyCode::W),
+            scan_code: 17,
+            state: ButtonState::Pressed,
+        });
+        engine.send_event(MouseButtonInput {
+            button: MouseButton::Left,
+            state: ButtonState::Pressed,
+        });
+        engine.update();
+
+        // The releases happening while unfocused are never received
+        engine.send_event(WindowFocused {
+            window: WindowId::Primary,
+            focused: false,
+        });
+        engine.update();
+
+        let ecs = engine.ecs();
+        let keys = ecs.resource::<Input<KeyCode>>().unwrap();
+        assert!(!keys.pressed(KeyCode::W));
+        assert!(keys.just_released(KeyCode::W));
+        let buttons = ecs.resource::<Input<MouseButton>>().unwrap();
+        assert!(!buttons.pressed(MouseButton::Left));
+        assert!(buttons.just_released(MouseButton::Left));
+    }
+}
@@ -0,0 +1,110 @@
+use butter_math::vector::Vector2;
+
+use crate::{
+    ecs::{event::EventReader, resource::ResMut},
+    window::{WindowFocused, WindowId},
+};
+
+use super::{ButtonState, Input};
+
+pub use winit::event::MouseButton;
+
+/// Event sent when a mouse button is pressed or released
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MouseButtonInput {
+    pub button: MouseButton,
+    pub state: ButtonState,
+}
+
+/// Event sent when the cursor moves over a window
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CursorMoved {
+    pub window: WindowId,
+    /// In physical pixels from the top left corner of the window
+    pub position: Vector2<f32>,
+}
+
+/// Event sent when the cursor leaves a window
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CursorLeft {
+    pub window: WindowId,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MouseScrollUnit {
+    Line,
+    Pixel,
+}
+
+/// Event sent when the mouse wheel or the touchpad scrolls
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MouseWheel {
+    pub delta: Vector2<f32>,
+    pub unit: MouseScrollUnit,
+}
+
+/// Event sent when the mouse moves, even outside of the window, which suits
+/// camera controls better than [`CursorMoved`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MouseMotion {
+    pub delta: Vector2<f32>,
+}
+
+/// A resource giving the window the cursor is over and its position there,
+/// updated by the runner as soon as the cursor moves or leaves a window
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Cursor {
+    window: Option<WindowId>,
+    position: Option<Vector2<f32>>,
+}
+
+impl Cursor {
+    /// The window the cursor is over, `None` when it is outside of every
+    /// window
+    #[must_use]
+    pub fn window(&self) -> Option<WindowId> {
+        self.window
+    }
+
+    /// In physical pixels from the top left corner of the
+    /// [window](Self::window), `None` when the cursor is outside of every
+    /// window
+    #[must_use]
+    pub fn position(&self) -> Option<Vector2<f32>> {
+        self.position
+    }
+
+    /// Moves the cursor over the window, or out of it, leaving a window
+    /// not changing the cursor once it has entered another
+    pub(crate) fn move_to(&mut self, window: WindowId, position: Option<Vector2<f32>>) {
+        if position.is_some() {
+            self.window = Some(window);
+            self.position = position;
+        } else if self.window == Some(window) {
+            self.window = None;
+            self.position = None;
+        }
+    }
+}
+
+/// Updates the [`Input<MouseButton>`] from the [`MouseButtonInput`] events,
+/// releasing every button when a window loses the focus
+pub fn mouse_input_system(
+    mut buttons: ResMut<Input<MouseButton>>,
+    mut mouse_button_inputs: EventReader<MouseButtonInput>,
+    mut focus_changes: EventReader<WindowFocused>,
+) {
+    buttons.clear();
+    for mouse_button_input in mouse_button_inputs.read() {
+        match mouse_button_input.state {
+            ButtonState::Pressed => buttons.press(mouse_button_input.button),
+            ButtonState::Released => buttons.release(mouse_button_input.button),
+        }
+    }
+    if focus_changes
+        .read()
+        .any(|focus_change| !focus_change.focused)
+    {
+        buttons.release_all();
+    }
+}
@@ -1,9 +1,26 @@
 #![warn(clippy::pedantic)]
 
-use butter_ecs::{system, Ecs};
+use std::{
+    any::TypeId,
+    collections::{HashMap, HashSet},
+};
+
+use butter_ecs::{diagnostics::SystemError, event::Events, system, Ecs};
+use butter_math::vector::Vector2;
+use plugin::{Plugin, PluginGroup};
+use time::{Clock, FixedTime, Time};
 
 pub use butter_ecs as ecs;
 pub use butter_graphics as graphics;
+pub use settings::Settings;
+pub mod headless;
+pub mod input;
+pub mod lifecycle;
+pub mod plugin;
+pub mod rendering;
+pub mod settings;
+pub mod state;
+pub mod time;
 pub mod window;
 pub mod winit;
 
@@ -11,106 +28,585 @@ pub mod winit;
 pub struct ButterEngine {
     settings: Settings,
     graphic_state: Option<graphics::State>,
+    surfaces: HashMap<window::WindowId, graphics::Surface>,
     init_systems: Vec<Box<dyn system::System>>,
+    first_systems: Vec<Box<dyn system::System>>,
+    pre_update_systems: Vec<Box<dyn system::System>>,
+    fixed_systems: Vec<Box<dyn system::System>>,
     systems: Vec<Box<dyn system::System>>,
+    shutdown_systems: Vec<Box<dyn system::System>>,
+    state_transitions: Vec<fn(&mut Ecs)>,
     ecs: Ecs,
+    clock: Clock,
+    system_error_handler: Option<fn(&SystemError)>,
 }
 
 impl ButterEngine {
-    pub(crate) fn settings(&self) -> &Settings {
+    #[must_use]
+    pub fn settings(&self) -> &Settings {
         &self.settings
     }
 
+    pub(crate) fn graphic_state(&self) -> Option<&graphics::State> {
+        self.graphic_state.as_ref()
+    }
+
     pub(crate) fn set_graphic_state(&mut self, graphic_state: graphics::State) {
         self.graphic_state = Some(graphic_state);
     }
 
-    pub(crate) fn init(&mut self) {
+    /// Renders the window to the surface from now on
+    pub(crate) fn add_surface(&mut self, window: window::WindowId, surface: graphics::Surface) {
+        self.surfaces.insert(window, surface);
+    }
+
+    /// Stops rendering the window, which must be done before it is closed
+    pub(crate) fn remove_surface(&mut self, window: window::WindowId) {
+        self.surfaces.remove(&window);
+    }
+
+    pub(crate) fn set_vsync(&mut self, window: window::WindowId, vsync: bool) {
+        if let (Some(graphic_state), Some(surface)) =
+            (self.graphic_state.as_ref(), self.surfaces.get_mut(&window))
+        {
+            graphic_state.set_vsync(surface, vsync);
+        }
+    }
+
+    /// The [`window::Window`] resource or component identified by `window`
+    pub(crate) fn window(&self, window: window::WindowId) -> Option<&window::Window> {
+        match window {
+            window::WindowId::Primary => self.ecs.resource(),
+            window::WindowId::Entity(entity_index) => {
+                if self.ecs.contains(entity_index) {
+                    self.ecs.component(entity_index)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    fn window_mut(&mut self, window: window::WindowId) -> Option<&mut window::Window> {
+        match window {
+            window::WindowId::Primary => self.ecs.resource_mut(),
+            window::WindowId::Entity(entity_index) => {
+                if self.ecs.contains(entity_index) {
+                    self.ecs.component_mut(entity_index)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Reconfigures the surface and updates the [`window::Window`] after the
+    /// platform resized the window
+    pub(crate) fn resize(
+        &mut self,
+        window: window::WindowId,
+        size: window::Size,
+        scale_factor: f64,
+    ) {
+        if let (Some(graphic_state), Some(surface)) =
+            (self.graphic_state.as_ref(), self.surfaces.get_mut(&window))
+        {
+            graphic_state.resize(surface, (size.width, size.height));
+        }
+        let scale_factor_changed = self.window_mut(window).is_some_and(|resized_window| {
+            let scale_factor_changed =
+                (resized_window.scale_factor() - scale_factor).abs() > f64::EPSILON;
+            resized_window.resized(size, scale_factor);
+            scale_factor_changed
+        });
+        if scale_factor_changed {
+            self.send_event(window::WindowScaleFactorChanged {
+                window,
+                scale_factor,
+            });
+        }
+        self.send_event(window::WindowResized { window, size });
+    }
+
+    /// Runs the init systems then the enter systems of the initial states,
+    /// which runners do before the first update
+    pub fn init(&mut self) {
+        self.ecs.clear_system_diagnostics();
         self.ecs.run_systems(&mut self.init_systems);
+        self.apply_state_transitions();
+        self.report_system_errors();
     }
 
-    pub(crate) fn update(&mut self) {
+    /// Updates the [`Time`], the events and the input, runs the
+    /// [pre-update systems](ButterEngineBuilder::with_pre_update_system),
+    /// applies the state transitions, runs the fixed systems for each
+    /// elapsed [`FixedTime`] step then the other systems, like a runner does
+    /// every frame
+    ///
+    /// The [diagnostics](Ecs::system_timings) of the systems cover the whole
+    /// frame.
+    pub fn update(&mut self) {
+        self.ecs.clear_system_diagnostics();
+        let now = self.clock.now();
+        if let Some(time) = self.ecs.resource_mut::<Time>() {
+            time.update(now);
+            let delta = time.delta();
+            if let Some(fixed_time) = self.ecs.resource_mut::<FixedTime>() {
+                fixed_time.accumulate(delta);
+            }
+        }
+        self.ecs.run_systems(&mut self.first_systems);
+        self.ecs.run_systems(&mut self.pre_update_systems);
+        self.apply_state_transitions();
+
+        while self
+            .ecs
+            .resource_mut::<FixedTime>()
+            .is_some_and(FixedTime::expend)
+        {
+            self.ecs.run_systems(&mut self.fixed_systems);
+        }
         self.ecs.run_systems(&mut self.systems);
+        self.report_system_errors();
     }
 
-    /// Renders
-    ///
-    /// # Panics
-    ///
-    /// Will panic if the graphic state is not set
-    pub(crate) fn render(&mut self) {
-        self.graphic_state.as_mut().unwrap().render();
+    fn apply_state_transitions(&mut self) {
+        for apply_state_transition in &self.state_transitions {
+            apply_state_transition(&mut self.ecs);
+        }
+    }
+
+    /// Runs the shutdown systems, which runners do once before stopping,
+    /// later calls doing nothing
+    pub fn shutdown(&mut self) {
+        let mut shutdown_systems = std::mem::take(&mut self.shutdown_systems);
+        if shutdown_systems.is_empty() {
+            return;
+        }
+        self.ecs.clear_system_diagnostics();
+        self.ecs.run_systems(&mut shutdown_systems);
+        self.report_system_errors();
+    }
+
+    /// Passes the errors returned by the systems since the diagnostics were
+    /// cleared to the handler, which logs them by default
+    fn report_system_errors(&self) {
+        let system_error_handler = self.system_error_handler.unwrap_or(log_system_error);
+        for system_error in self.ecs.system_errors() {
+            system_error_handler(system_error);
+        }
+    }
+
+    /// Updates the [`input::Cursor`] and sends a [`input::CursorMoved`] or
+    /// [`input::CursorLeft`] event when the cursor moves over a window or
+    /// leaves it, the last change of a frame deciding where the cursor is
+    pub(crate) fn move_cursor(&mut self, window: window::WindowId, position: Option<Vector2<f32>>) {
+        if let Some(cursor) = self.ecs.resource_mut::<input::Cursor>() {
+            cursor.move_to(window, position);
+        }
+        match position {
+            Some(position) => self.send_event(input::CursorMoved { window, position }),
+            None => self.send_event(input::CursorLeft { window }),
+        }
+    }
+
+    /// Whether an [`AppExit`] event has been sent during the last two frames
+    #[must_use]
+    pub fn should_exit(&self) -> bool {
+        self.ecs
+            .resource::<Events<AppExit>>()
+            .is_some_and(|events| !events.is_empty())
+    }
+
+    /// Sends an event if its type has been registered, which is how the
+    /// runners forward the window and input events
+    pub fn send_event<E>(&mut self, event: E)
+    where
+        E: Send + Sync + 'static,
+    {
+        if let Some(events) = self.ecs.resource_mut::<Events<E>>() {
+            events.send(event);
+        }
+    }
+
+    #[must_use]
+    pub fn ecs(&self) -> &Ecs {
+        &self.ecs
+    }
+
+    pub fn ecs_mut(&mut self) -> &mut Ecs {
+        &mut self.ecs
+    }
+
+    /// Renders the window, if it has a surface
+    pub(crate) fn render(&mut self, window: window::WindowId) {
+        if let (Some(graphic_state), Some(surface)) =
+            (self.graphic_state.as_ref(), self.surfaces.get_mut(&window))
+        {
+            graphic_state.render(surface);
+        }
     }
 }
 
+fn log_system_error(system_error: &SystemError) {
+    eprintln!("{system_error}");
+}
+
+/// Event asking the runner to stop the engine after running the shutdown
+/// systems, also sent when the window is closed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AppExit;
+
 #[derive(Default)]
-pub struct ButterEngineBuilder<'a> {
-    window_title: Option<&'a str>,
-    window_size: Option<window::Size>,
-    wasm_canvas_id: Option<&'a str>,
+pub struct ButterEngineBuilder {
+    settings: Settings,
+    plugins: HashSet<TypeId>,
+    ecs: Ecs,
+    clock: Clock,
     init_systems: Vec<Box<dyn system::System>>,
+    fixed_systems: Vec<Box<dyn system::System>>,
+    /// Run at the start of each frame, before the other systems
+    first_systems: Vec<Box<dyn system::System>>,
+    /// Run after the first systems, once the input is up to date
+    pre_update_systems: Vec<Box<dyn system::System>>,
     systems: Vec<Box<dyn system::System>>,
+    shutdown_systems: Vec<Box<dyn system::System>>,
+    state_transitions: Vec<fn(&mut Ecs)>,
+    system_error_handler: Option<fn(&SystemError)>,
 }
 
-impl<'a> ButterEngineBuilder<'a> {
+impl ButterEngineBuilder {
     #[must_use]
     pub fn new() -> Self {
         Self::default()
     }
 
-    pub fn with_window_title(&mut self, window_title: &'a str) -> &mut Self {
-        self.window_title = Some(window_title);
+    /// Replaces all the settings, like the ones loaded by
+    /// [`Settings::load`], the `with_window_*` methods called after it
+    /// overriding them
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the settings are [invalid](Settings::validate),
+    /// the current ones being kept
+    pub fn with_settings(
+        &mut self,
+        settings: Settings,
+    ) -> Result<&mut Self, settings::SettingsError> {
+        settings.validate()?;
+        self.settings = settings;
+        Ok(self)
+    }
+
+    pub fn with_window_title(&mut self, window_title: impl Into<String>) -> &mut Self {
+        self.settings.window.title = window_title.into();
         self
     }
 
     pub fn with_window_size(&mut self, window_size: window::Size) -> &mut Self {
-        self.window_size = Some(window_size);
+        self.settings.window.size = window_size;
+        self
+    }
+
+    pub fn with_window_mode(&mut self, window_mode: window::WindowMode) -> &mut Self {
+        self.settings.window.mode = window_mode;
+        self
+    }
+
+    pub fn with_resizable(&mut self, resizable: bool) -> &mut Self {
+        self.settings.window.resizable = resizable;
+        self
+    }
+
+    pub fn with_vsync(&mut self, vsync: bool) -> &mut Self {
+        self.settings.window.vsync = vsync;
+        self
+    }
+
+    pub fn with_wasm_canvas_id(&mut self, wasm_canvas_id: impl Into<String>) -> &mut Self {
+        self.settings.window.wasm_canvas_id = wasm_canvas_id.into();
+        self
+    }
+
+    /// Sets what happens when a system panics, see [`system::PanicPolicy`]
+    pub fn with_panic_policy(&mut self, panic_policy: system::PanicPolicy) -> &mut Self {
+        self.ecs.set_panic_policy(panic_policy);
         self
     }
 
-    pub fn with_wasm_canvas_id(&mut self, wasm_canvas_id: &'a str) -> &mut Self {
-        self.wasm_canvas_id = Some(wasm_canvas_id);
+    /// Sets the function called with each error returned by a system, at
+    /// the end of the frame, instead of printing it to the standard error
+    pub fn with_system_error_handler(
+        &mut self,
+        system_error_handler: fn(&SystemError),
+    ) -> &mut Self {
+        self.system_error_handler = Some(system_error_handler);
         self
     }
 
-    pub fn with_init_system<S, P>(&mut self, init_system: S) -> &mut Self
+    /// # Panics
+    ///
+    /// Will panic if the plugin is [unique](Plugin::is_unique) and has
+    /// already been registered
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn with_plugin<P>(&mut self, plugin: P) -> &mut Self
     where
-        S: system::Into<P>,
-        P: system::Parameter,
-        <S as system::Into<P>>::SystemType: system::System,
+        P: Plugin,
     {
-        self.init_systems.push(Box::new(init_system.into_system()));
+        self.add_plugin(&plugin);
         self
     }
 
-    pub fn with_system<S, P>(&mut self, system: S) -> &mut Self
+    /// Registers the enabled plugins of a group, see [`with_plugin`](Self::with_plugin)
+    pub fn with_plugins<G>(&mut self, plugin_group: G) -> &mut Self
     where
-        S: system::Into<P>,
-        P: system::Parameter,
-        <S as ecs::system::Into<P>>::SystemType: ecs::system::System,
+        G: PluginGroup,
     {
-        self.systems.push(Box::new(system.into_system()));
+        for plugin in plugin_group.build().into_enabled() {
+            self.add_plugin(plugin.as_ref());
+        }
+        self
+    }
+
+    fn add_plugin(&mut self, plugin: &dyn Plugin) {
+        let is_new = self.plugins.insert(plugin.type_id());
+        assert!(
+            is_new || !plugin.is_unique(),
+            "Plugin {} is already registered",
+            plugin.name()
+        );
+        plugin.build(self);
+    }
+
+    pub fn with_resource<R>(&mut self, resource: R) -> &mut Self
+    where
+        R: Send + Sync + 'static,
+    {
+        self.ecs.insert_resource(resource);
+        self
+    }
+
+    /// Inserts the default value of the resource unless it is already
+    /// registered, for plugins reading the resources of another one
+    pub(crate) fn with_default_resource<R>(&mut self) -> &mut Self
+    where
+        R: Default + Send + Sync + 'static,
+    {
+        if !self.ecs.contains_resource::<R>() {
+            self.ecs.insert_resource(R::default());
+        }
+        self
+    }
+
+    pub fn with_non_send_resource<R>(&mut self, resource: R) -> &mut Self
+    where
+        R: 'static,
+    {
+        self.ecs.insert_non_send_resource(resource);
+        self
+    }
+
+    /// Registers the [`Events`] resource of type `E`, updated at the start of
+    /// each frame
+    pub fn with_event<E>(&mut self) -> &mut Self
+    where
+        E: Send + Sync + 'static,
+    {
+        if !self.ecs.contains_resource::<Events<E>>() {
+            self.ecs.insert_resource(Events::<E>::new());
+            self.with_first_system(Events::<E>::update_system);
+        }
+        self
+    }
+
+    /// Adds a system run at the start of each frame, after the update of the
+    /// events and before the other systems, like the ones updating the input
+    pub(crate) fn with_first_system<S, M>(&mut self, system: S) -> &mut Self
+    where
+        S: system::Into<M>,
+        <S as system::Into<M>>::SystemType: system::System,
+    {
+        self.first_systems.push(Box::new(system.into_system()));
+        self
+    }
+
+    pub fn with_init_system<S, M, O>(&mut self, init_system: S) -> &mut Self
+    where
+        S: system::Into<M>,
+        <S as system::Into<M>>::SystemType: system::System<(), O>,
+        O: system::Report,
+    {
+        self.init_systems
+            .push(Box::new(system::Reported::new(init_system.into_system())));
+        self
+    }
+
+    /// Adds a system run every frame, which may return a `Result` whose
+    /// errors are reported by [`Ecs::system_errors`] and passed to the
+    /// [error handler](Self::with_system_error_handler)
+    pub fn with_system<S, M, O>(&mut self, system: S) -> &mut Self
+    where
+        S: system::Into<M>,
+        <S as system::Into<M>>::SystemType: system::System<(), O>,
+        O: system::Report,
+    {
+        self.systems
+            .push(Box::new(system::Reported::new(system.into_system())));
+        self
+    }
+
+    /// Adds a system run every frame once the input is up to date, before
+    /// the state transitions and the other systems, like the ones turning
+    /// the input into game actions, see [`with_system`](Self::with_system)
+    pub fn with_pre_update_system<S, M, O>(&mut self, system: S) -> &mut Self
+    where
+        S: system::Into<M>,
+        <S as system::Into<M>>::SystemType: system::System<(), O>,
+        O: system::Report,
+    {
+        self.pre_update_systems
+            .push(Box::new(system::Reported::new(system.into_system())));
+        self
+    }
+
+    /// Adds a system run at the fixed rate of the [`FixedTime`] resource,
+    /// see [`with_system`](Self::with_system)
+    pub fn with_fixed_system<S, M, O>(&mut self, system: S) -> &mut Self
+    where
+        S: system::Into<M>,
+        <S as system::Into<M>>::SystemType: system::System<(), O>,
+        O: system::Report,
+    {
+        self.fixed_systems
+            .push(Box::new(system::Reported::new(system.into_system())));
+        self
+    }
+
+    /// Adds a system run once when the engine stops, to flush saves or close
+    /// connections, see [`with_system`](Self::with_system)
+    pub fn with_shutdown_system<S, M, O>(&mut self, shutdown_system: S) -> &mut Self
+    where
+        S: system::Into<M>,
+        <S as system::Into<M>>::SystemType: system::System<(), O>,
+        O: system::Report,
+    {
+        self.shutdown_systems.push(Box::new(system::Reported::new(
+            shutdown_system.into_system(),
+        )));
+        self
+    }
+
+    /// Registers the [`State`](state::State) and
+    /// [`NextState`](state::NextState) resources of type `S`
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the state is already registered
+    pub fn with_state<S>(&mut self, initial_state: S) -> &mut Self
+    where
+        S: state::States,
+    {
+        assert!(
+            !self.ecs.contains_resource::<state::State<S>>(),
+            "State {} is already registered",
+            std::any::type_name::<S>()
+        );
+        state::insert_state(&mut self.ecs, initial_state);
+        self.state_transitions
+            .push(state::apply_state_transition::<S>);
+        self
+    }
+
+    /// Adds a system run when entering the state, including the initial one
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the state isn't registered
+    pub fn with_system_on_enter<S, Sys, M, O>(&mut self, state: S, system: Sys) -> &mut Self
+    where
+        S: state::States,
+        Sys: system::Into<M>,
+        <Sys as system::Into<M>>::SystemType: system::System<(), O>,
+        O: system::Report,
+    {
+        self.state_transitions_mut::<S>()
+            .add_on_enter(state, Box::new(system::Reported::new(system.into_system())));
+        self
+    }
+
+    /// Adds a system run when exiting the state, before the entities
+    /// [scoped](state::StateScoped) to it are despawned
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the state isn't registered
+    pub fn with_system_on_exit<S, Sys, M, O>(&mut self, state: S, system: Sys) -> &mut Self
+    where
+        S: state::States,
+        Sys: system::Into<M>,
+        <Sys as system::Into<M>>::SystemType: system::System<(), O>,
+        O: system::Report,
+    {
+        self.state_transitions_mut::<S>()
+            .add_on_exit(state, Box::new(system::Reported::new(system.into_system())));
+        self
+    }
+
+    fn state_transitions_mut<S>(&mut self) -> &mut state::StateTransitions<S>
+    where
+        S: state::States,
+    {
+        self.ecs
+            .non_send_resource_mut::<state::StateTransitions<S>>()
+            .unwrap_or_else(|| panic!("State {} is not registered", std::any::type_name::<S>()))
+    }
+
+    /// Sets the clock the [`Time`] is read from, which can be a
+    /// [manual](Clock::manual) one in tests
+    pub fn with_clock(&mut self, clock: Clock) -> &mut Self {
+        self.clock = clock;
         self
     }
 
     pub fn build(&mut self) -> ButterEngine {
+        self.with_event::<AppExit>()
+            .with_event::<window::WindowResized>()
+            .with_event::<window::WindowScaleFactorChanged>()
+            .with_event::<window::WindowFocused>()
+            .with_event::<lifecycle::AppLifecycle>();
+        if !self.ecs.contains_resource::<window::Window>() {
+            self.ecs
+                .insert_resource(window::Window::new(&self.settings.window));
+        }
+        if !self.ecs.contains_resource::<Time>() {
+            let mut time = Time::new();
+            time.set_scale(self.settings.time.scale);
+            self.ecs.insert_resource(time);
+        }
+        if !self.ecs.contains_resource::<FixedTime>() {
+            self.ecs
+                .insert_resource(FixedTime::from_hz(self.settings.time.fixed_timestep_hz));
+        }
+        if !self.ecs.contains_resource::<lifecycle::LifecycleSettings>() {
+            self.ecs
+                .insert_resource(lifecycle::LifecycleSettings::default());
+        }
+        self.with_first_system(lifecycle::pause_time_system);
+
         ButterEngine {
-            settings: Settings {
-                window_settings: window::Settings {
-                    title: self.window_title.unwrap_or("Butter App").into(),
-                    size: self.window_size.unwrap_or_default(),
-                    wasm_canvas_id: self.wasm_canvas_id.unwrap_or("butter-app").into(),
-                },
-            },
+            settings: self.settings.clone(),
             init_systems: self.init_systems.drain(..).collect(),
+            fixed_systems: self.fixed_systems.drain(..).collect(),
+            first_systems: self.first_systems.drain(..).collect(),
+            pre_update_systems: self.pre_update_systems.drain(..).collect(),
             systems: self.systems.drain(..).collect(),
+            shutdown_systems: self.shutdown_systems.drain(..).collect(),
+            state_transitions: self.state_transitions.drain(..).collect(),
             graphic_state: None,
-            ecs: Ecs::new(),
+            surfaces: HashMap::new(),
+            ecs: std::mem::take(&mut self.ecs),
+            clock: self.clock.clone(),
+            system_error_handler: self.system_error_handler,
         }
     }
 }
-
-#[derive(Default)]
-pub struct Settings {
-    pub window_settings: window::Settings,
-}
@@ -0,0 +1,193 @@
+use crate::{
+    ecs::{
+        event::EventReader,
+        resource::{Res, ResMut},
+        system::Local,
+    },
+    time::Time,
+    window::{WindowFocused, WindowId},
+};
+
+/// Event sent when the application is suspended or resumed by the platform,
+/// like on mobile when it goes to the background
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AppLifecycle {
+    Suspended,
+    Resumed,
+}
+
+/// A resource telling when the [`Time`] is paused by the lifecycle events
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LifecycleSettings {
+    pub pause_on_focus_lost: bool,
+    pub pause_on_suspend: bool,
+}
+
+impl Default for LifecycleSettings {
+    fn default() -> Self {
+        Self {
+            pause_on_focus_lost: false,
+            pause_on_suspend: true,
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct PauseReasons {
+    unfocused: bool,
+    /// The window which last gained the focus and hasn't lost it since
+    focused_window: Option<WindowId>,
+    suspended: bool,
+    /// Set while the lifecycle events pause the time, to whether it was
+    /// already paused by another system, in which case it stays paused
+    /// after them
+    paused_before: Option<bool>,
+}
+
+/// Pauses the [`Time`] while no window is focused or the application is
+/// suspended, as configured by the [`LifecycleSettings`]
+#[allow(clippy::needless_pass_by_value)]
+pub(crate) fn pause_time_system(
+    settings: Res<LifecycleSettings>,
+    mut time: ResMut<Time>,
+    mut focus_changes: EventReader<WindowFocused>,
+    mut lifecycle_changes: EventReader<AppLifecycle>,
+    mut reasons: Local<PauseReasons>,
+) {
+    for focus_change in focus_changes.read() {
+        if focus_change.focused {
+            reasons.focused_window = Some(focus_change.window);
+        } else if reasons
+            .focused_window
+            .is_none_or(|window| window == focus_change.window)
+        {
+            // Whether the focus moves to another window before or after
+            // this one loses it depends on the platform
+            reasons.focused_window = None;
+        }
+        reasons.unfocused = settings.pause_on_focus_lost && reasons.focused_window.is_none();
+    }
+    for &lifecycle_change in lifecycle_changes.read() {
+        reasons.suspended =
+            settings.pause_on_suspend && lifecycle_change == AppLifecycle::Suspended;
+    }
+
+    let pause = reasons.unfocused || reasons.suspended;
+    match reasons.paused_before {
+        None if pause => {
+            reasons.paused_before = Some(time.is_paused());
+            time.pause();
+        }
+        Some(paused_before) if !pause => {
+            if !paused_before {
+                time.unpause();
+            }
+            reasons.paused_before = None;
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        window::{self, Window},
+        ButterEngineBuilder,
+    };
+
+    #[test]
+    fn pause_on_lifecycle_events() {
+        let mut engine = ButterEngineBuilder::new()
+            .with_resource(LifecycleSettings {
+                pause_on_focus_lost: true,
+                pause_on_suspend: true,
+            })
+            .build();
+        engine.init();
+
+        let is_paused =
+            |engine: &crate::ButterEngine| engine.ecs().resource::<Time>().unwrap().is_paused();
+        engine.send_event(WindowFocused {
+            window: WindowId::Primary,
+            focused: false,
+        });
+        engine.update();
+        assert!(is_paused(&engine));
+
+        engine.send_event(AppLifecycle::Suspended);
+        engine.send_event(WindowFocused {
+            window: WindowId::Primary,
+            focused: true,
+        });
+        engine.update();
+        assert!(is_paused(&engine));
+
+        engine.send_event(AppLifecycle::Resumed);
+        engine.update();
+        assert!(!is_paused(&engine));
+
+        // A pause made by the game outlasts the lifecycle one
+        engine.ecs_mut().resource_mut::<Time>().unwrap().pause();
+        engine.send_event(WindowFocused {
+            window: WindowId::Primary,
+            focused: false,
+        });
+        engine.update();
+        engine.send_event(WindowFocused {
+            window: WindowId::Primary,
+            focused: true,
+        });
+        engine.update();
+        assert!(is_paused(&engine));
+
+        // Focus changes are ignored by default
+        let mut engine = ButterEngineBuilder::new().build();
+        engine.init();
+        engine.send_event(WindowFocused {
+            window: WindowId::Primary,
+            focused: false,
+        });
+        engine.update();
+        assert!(!is_paused(&engine));
+    }
+
+    #[test]
+    fn focus_moving_between_windows() {
+        let mut engine = ButterEngineBuilder::new()
+            .with_resource(LifecycleSettings {
+                pause_on_focus_lost: true,
+                pause_on_suspend: true,
+            })
+            .build();
+        engine.init();
+        let other_window = WindowId::Entity(
+            engine
+                .ecs_mut()
+                .insert((Window::new(&window::Settings::default()),)),
+        );
+
+        // The other window gains the focus before the primary one loses it
+        engine.send_event(WindowFocused {
+            window: WindowId::Primary,
+            focused: true,
+        });
+        engine.send_event(WindowFocused {
+            window: other_window,
+            focused: true,
+        });
+        engine.send_event(WindowFocused {
+            window: WindowId::Primary,
+            focused: false,
+        });
+        engine.update();
+        assert!(!engine.ecs().resource::<Time>().unwrap().is_paused());
+
+        engine.send_event(WindowFocused {
+            window: other_window,
+            focused: false,
+        });
+        engine.update();
+        assert!(engine.ecs().resource::<Time>().unwrap().is_paused());
+    }
+}
@@ -0,0 +1,226 @@
+use std::any::{Any, TypeId};
+
+use crate::ButterEngineBuilder;
+
+/// A feature of the engine, like the input or the time, registering its
+/// systems, resources and events as a unit
+///
+/// ```
+/// # use butter::{plugin::Plugin, ButterEngineBuilder};
+/// struct Score(u32);
+///
+/// struct ScorePlugin;
+///
+/// impl Plugin for ScorePlugin {
+///     fn build(&self, builder: &mut ButterEngineBuilder) {
+///         builder.with_resource(Score(0));
+///     }
+/// }
+///
+/// ButterEngineBuilder::new().with_plugin(ScorePlugin).build();
+/// ```
+pub trait Plugin: Any {
+    fn build(&self, builder: &mut ButterEngineBuilder);
+
+    /// Whether registering the plugin twice is a mistake, which is the case by
+    /// default
+    fn is_unique(&self) -> bool {
+        true
+    }
+
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// Plugins registered together, some of which can be disabled
+///
+/// ```
+/// # use butter::{plugin::{Plugin, PluginGroup, PluginGroupBuilder}, ButterEngineBuilder};
+/// # struct AudioPlugin;
+/// # impl Plugin for AudioPlugin {
+/// #     fn build(&self, _builder: &mut ButterEngineBuilder) {}
+/// # }
+/// # struct PhysicsPlugin;
+/// # impl Plugin for PhysicsPlugin {
+/// #     fn build(&self, _builder: &mut ButterEngineBuilder) {}
+/// # }
+/// struct GamePlugins;
+///
+/// impl PluginGroup for GamePlugins {
+///     fn build(self) -> PluginGroupBuilder {
+///         PluginGroupBuilder::new()
+///             .with_plugin(AudioPlugin)
+///             .with_plugin(PhysicsPlugin)
+///     }
+/// }
+///
+/// ButterEngineBuilder::new()
+///     .with_plugins(GamePlugins.build().disable::<AudioPlugin>())
+///     .build();
+/// ```
+pub trait PluginGroup {
+    fn build(self) -> PluginGroupBuilder;
+}
+
+pub struct PluginGroupBuilder {
+    plugins: Vec<(Box<dyn Plugin>, bool)>,
+}
+
+impl PluginGroupBuilder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self { plugins: vec![] }
+    }
+
+    #[must_use]
+    pub fn with_plugin<P>(mut self, plugin: P) -> Self
+    where
+        P: Plugin,
+    {
+        self.plugins.push((Box::new(plugin), true));
+        self
+    }
+
+    /// Keeps the plugins of type `P` from being registered
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the group has no such plugin
+    #[must_use]
+    pub fn disable<P>(self) -> Self
+    where
+        P: Plugin,
+    {
+        self.set_enabled::<P>(false)
+    }
+
+    /// # Panics
+    ///
+    /// Will panic if the group has no such plugin
+    #[must_use]
+    pub fn enable<P>(self) -> Self
+    where
+        P: Plugin,
+    {
+        self.set_enabled::<P>(true)
+    }
+
+    fn set_enabled<P>(mut self, enabled: bool) -> Self
+    where
+        P: Plugin,
+    {
+        let mut found = false;
+        for (plugin, plugin_enabled) in &mut self.plugins {
+            if plugin.as_ref().type_id() == TypeId::of::<P>() {
+                *plugin_enabled = enabled;
+                found = true;
+            }
+        }
+        assert!(
+            found,
+            "Plugin {} is not part of the group",
+            std::any::type_name::<P>()
+        );
+        self
+    }
+
+    pub(crate) fn into_enabled(self) -> impl Iterator<Item = Box<dyn Plugin>> {
+        self.plugins
+            .into_iter()
+            .filter_map(|(plugin, enabled)| enabled.then_some(plugin))
+    }
+}
+
+impl Default for PluginGroupBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PluginGroup for PluginGroupBuilder {
+    fn build(self) -> PluginGroupBuilder {
+        self
+    }
+}
+
+/// The plugins of the features of the engine
+pub struct DefaultPlugins;
+
+impl PluginGroup for DefaultPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::new()
+            .with_plugin(crate::input::InputPlugin)
+            .with_plugin(crate::input::GamepadPlugin::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    static BUILDS: AtomicU32 = AtomicU32::new(0);
+
+    struct Score(u32);
+
+    struct ScorePlugin;
+
+    impl Plugin for ScorePlugin {
+        fn build(&self, builder: &mut ButterEngineBuilder) {
+            builder.with_resource(Score(7));
+        }
+    }
+
+    struct CountingPlugin;
+
+    impl Plugin for CountingPlugin {
+        fn build(&self, _builder: &mut ButterEngineBuilder) {
+            BUILDS.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn is_unique(&self) -> bool {
+            false
+        }
+    }
+
+    struct GamePlugins;
+
+    impl PluginGroup for GamePlugins {
+        fn build(self) -> PluginGroupBuilder {
+            PluginGroupBuilder::new()
+                .with_plugin(CountingPlugin)
+                .with_plugin(ScorePlugin)
+        }
+    }
+
+    #[test]
+    fn plugin_groups() {
+        let engine = ButterEngineBuilder::new().with_plugins(GamePlugins).build();
+        assert_eq!(engine.ecs.resource::<Score>().map(|score| score.0), Some(7));
+
+        let engine = ButterEngineBuilder::new()
+            .with_plugins(GamePlugins.build().disable::<ScorePlugin>())
+            .build();
+        assert!(!engine.ecs.contains_resource::<Score>());
+    }
+
+    #[test]
+    fn non_unique_plugins() {
+        let before = BUILDS.load(Ordering::Relaxed);
+        ButterEngineBuilder::new()
+            .with_plugin(CountingPlugin)
+            .with_plugin(CountingPlugin)
+            .build();
+        assert!(BUILDS.load(Ordering::Relaxed) >= before + 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "is already registered")]
+    fn duplicate_plugins() {
+        ButterEngineBuilder::new()
+            .with_plugin(ScorePlugin)
+            .with_plugins(GamePlugins);
+    }
+}
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+use crate::graphics;
+
+/// The rendering options of the engine, part of its
+/// [`Settings`](crate::settings::Settings), the vsync being a
+/// [window option](crate::window::Settings::vsync) since every
+/// [`Window`](crate::window::Window) can change its own
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Samples per pixel of the multisample anti-aliasing, either 1 which
+    /// disables it or 4, falling back to 1 if the GPU doesn't support it
+    pub msaa_samples: u32,
+    pub power_preference: PowerPreference,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            msaa_samples: 1,
+            power_preference: PowerPreference::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub(crate) fn graphics_options(self) -> graphics::Options {
+        graphics::Options {
+            msaa_samples: self.msaa_samples,
+            power_preference: match self.power_preference {
+                PowerPreference::LowPower => graphics::PowerPreference::LowPower,
+                PowerPreference::HighPerformance => graphics::PowerPreference::HighPerformance,
+            },
+        }
+    }
+}
+
+/// Which GPU renders when there are several, like on laptops
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PowerPreference {
+    /// Usually the integrated GPU
+    #[default]
+    LowPower,
+    /// Usually the discrete GPU
+    HighPerformance,
+}
@@ -0,0 +1,376 @@
+use std::{fmt, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{rendering, time, window};
+
+/// Prefix of the environment variables overriding the settings, the sections
+/// being separated by two underscores, like `BUTTER_WINDOW__TITLE` for
+/// `window.title`
+pub const ENV_PREFIX: &str = "BUTTER_";
+
+/// The sections of the settings, whose options are the only ones read from
+/// the environment variables and the command line arguments
+const SECTIONS: [&str; 3] = ["window", "rendering", "time"];
+
+/// The options of the engine, whose defaults are the ones of its sections
+///
+/// They are set in code through the
+/// [`ButterEngineBuilder`](crate::ButterEngineBuilder), or loaded from a TOML
+/// file whose missing options keep their default value:
+///
+/// ```toml
+/// [window]
+/// title = "My game"
+/// size = { width = 1280, height = 720 }
+/// mode = "BorderlessFullscreen"
+/// vsync = false
+///
+/// [rendering]
+/// msaa_samples = 4
+/// power_preference = "HighPerformance"
+///
+/// [time]
+/// fixed_timestep_hz = 50.0
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub window: window::Settings,
+    pub rendering: rendering::Settings,
+    pub time: time::Settings,
+}
+
+impl Settings {
+    /// Loads the settings from the file, if it exists, then overrides them
+    /// with the environment variables and the command line arguments, see
+    /// [`apply_env`](Self::apply_env) and [`apply_args`](Self::apply_args)
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the file can't be read, or if the file or an
+    /// override is invalid
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SettingsError> {
+        let mut settings = match fs::read_to_string(path) {
+            Ok(settings) => Self::from_toml(&settings)?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(error) => return Err(error.into()),
+        };
+        // The variables and arguments which aren't valid UTF-8 can't be
+        // settings, unlike with `vars` and `args` they don't panic
+        settings.apply_env(std::env::vars_os().filter_map(|(name, value)| {
+            Some((name.into_string().ok()?, value.into_string().ok()?))
+        }))?;
+        settings.apply_args(
+            std::env::args_os()
+                .skip(1)
+                .filter_map(|arg| arg.into_string().ok()),
+        )?;
+        Ok(settings)
+    }
+
+    /// # Errors
+    ///
+    /// Will return an error if the string isn't valid settings
+    pub fn from_toml(settings: &str) -> Result<Self, SettingsError> {
+        let settings: Self = toml::from_str(settings)?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// # Panics
+    ///
+    /// Will panic if the settings can't be serialized, which can't happen
+    /// with valid settings
+    #[must_use]
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("Settings can't be serialized")
+    }
+
+    /// Sets the option at the path, like `window.title`, to the value, which
+    /// is parsed as TOML unless the option is a string
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if there is no such option or the value is
+    /// invalid, the settings being left unchanged
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the settings can't be serialized, which can't happen
+    /// with valid settings
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), SettingsError> {
+        let unknown_key = || SettingsError::UnknownKey(key.to_string());
+        let mut table = toml::Table::try_from(&*self).expect("Settings can't be serialized");
+
+        let (sections, name) = key.rsplit_once('.').map_or(("", key), |split| split);
+        let mut section = &mut table;
+        for section_name in sections.split('.').filter(|name| !name.is_empty()) {
+            section = section
+                .get_mut(section_name)
+                .and_then(toml::Value::as_table_mut)
+                .ok_or_else(unknown_key)?;
+        }
+        let value = match section.get(name).ok_or_else(unknown_key)? {
+            toml::Value::String(_) => toml::Value::String(value.to_string()),
+            _ => parse_value(value),
+        };
+        section.insert(name.to_string(), value);
+
+        let settings: Self = table.try_into()?;
+        settings.validate()?;
+        *self = settings;
+        Ok(())
+    }
+
+    /// Overrides the settings with the variables starting with
+    /// [`ENV_PREFIX`] followed by a section, usually read from
+    /// [`std::env::vars`]
+    ///
+    /// The other variables, like `BUTTER_LOG`, are left to the application.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if a variable doesn't name an option of its
+    /// section or its value is invalid
+    pub fn apply_env<I>(&mut self, vars: I) -> Result<(), SettingsError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        for (name, value) in vars {
+            let Some(key) = name.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            let key = key.to_lowercase().replace("__", ".");
+            if is_section_key(&key) {
+                self.set(&key, &value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Overrides the settings with the arguments like `--window.title=Game`
+    /// or `--window.title Game`, usually read from [`std::env::args`]
+    ///
+    /// The arguments whose name doesn't start with a section, like
+    /// `--verbose` or `--net.port 80`, are left to the application.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if an argument doesn't name an option of its
+    /// section, or its value is missing or invalid
+    pub fn apply_args<I>(&mut self, args: I) -> Result<(), SettingsError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let Some(option) = arg.strip_prefix("--") else {
+                continue;
+            };
+            let (key, value) = match option.split_once('=') {
+                Some((key, value)) => (key, Some(value)),
+                None => (option, None),
+            };
+            if !is_section_key(key) {
+                continue;
+            }
+            let value = match value {
+                Some(value) => value.to_string(),
+                None => args
+                    .next()
+                    .ok_or_else(|| SettingsError::MissingValue(key.to_string()))?,
+            };
+            self.set(key, &value)?;
+        }
+        Ok(())
+    }
+
+    /// Checks the options which can't be checked by their type, which
+    /// [`from_toml`](Self::from_toml), [`set`](Self::set) and
+    /// [`ButterEngineBuilder::with_settings`](crate::ButterEngineBuilder::with_settings)
+    /// do
+    ///
+    /// # Errors
+    ///
+    /// Will return an error naming the first invalid option
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if ![1, 4].contains(&self.rendering.msaa_samples) {
+            return Err(SettingsError::Invalid(
+                "rendering.msaa_samples must be 1 or 4".to_string(),
+            ));
+        }
+        let time = &self.time;
+        if !(time.fixed_timestep_hz.is_finite() && time.fixed_timestep_hz > 0.0) {
+            return Err(SettingsError::Invalid(
+                "time.fixed_timestep_hz must be strictly positive".to_string(),
+            ));
+        }
+        if !(time.scale.is_finite() && time.scale >= 0.0) {
+            return Err(SettingsError::Invalid(
+                "time.scale must be positive".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Whether the key, like `window.title`, starts with one of the
+/// [`SECTIONS`]
+fn is_section_key(key: &str) -> bool {
+    key.split_once('.')
+        .is_some_and(|(section, _)| SECTIONS.contains(&section))
+}
+
+/// Parses the value as TOML, like `60.0` or `{ width = 800, height = 600 }`,
+/// keeping it as a string if it isn't valid TOML, which then fails to
+/// deserialize with a clear error
+fn parse_value(value: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("value = {value}"))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(value.to_string()))
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    UnknownKey(String),
+    MissingValue(String),
+    Invalid(String),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "Settings can't be read: {error}"),
+            Self::Toml(error) => write!(f, "Settings are invalid: {error}"),
+            Self::UnknownKey(key) => write!(f, "Setting {key} doesn't exist"),
+            Self::MissingValue(key) => write!(f, "Setting {key} has no value"),
+            Self::Invalid(message) => write!(f, "Settings are invalid: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl From<io::Error> for SettingsError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<toml::de::Error> for SettingsError {
+    fn from(error: toml::de::Error) -> Self {
+        Self::Toml(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{time::FixedTime, window::Window, ButterEngineBuilder};
+
+    #[test]
+    fn settings_from_toml() {
+        let settings = Settings::from_toml(
+            r#"
+            [window]
+            title = "Game"
+            mode = "BorderlessFullscreen"
+
+            [time]
+            fixed_timestep_hz = 50.0
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(settings.window.title, "Game");
+        assert_eq!(
+            settings.window.mode,
+            window::WindowMode::BorderlessFullscreen
+        );
+        assert_eq!(settings.window.size, window::Size::default());
+        assert_eq!(settings.window.wasm_canvas_id, "butter-app");
+        assert!((settings.time.scale - 1.0).abs() < f64::EPSILON);
+        assert_eq!(Settings::from_toml(&settings.to_toml()).unwrap(), settings);
+        assert!(Settings::from_toml("[time]\nfixed_timestep_hz = 0.0").is_err());
+    }
+
+    #[test]
+    fn settings_overrides() {
+        let mut settings = Settings::default();
+        settings
+            .apply_env([
+                ("BUTTER_WINDOW__TITLE".to_string(), "true".to_string()),
+                ("BUTTER_TIME__SCALE".to_string(), "0.5".to_string()),
+                (
+                    "BUTTER_RENDERING__MSAA_SAMPLES".to_string(),
+                    "4".to_string(),
+                ),
+                ("HOME".to_string(), "/root".to_string()),
+                ("BUTTER_LOG".to_string(), "debug".to_string()),
+            ])
+            .unwrap();
+        settings
+            .apply_args(
+                [
+                    "--verbose",
+                    "--net.port",
+                    "80",
+                    "--window.vsync=false",
+                    "--rendering.power_preference=HighPerformance",
+                    "--window.size",
+                    "{ width = 1280, height = 720 }",
+                ]
+                .map(String::from),
+            )
+            .unwrap();
+
+        assert_eq!(settings.window.title, "true");
+        assert!((settings.time.scale - 0.5).abs() < f64::EPSILON);
+        assert!(!settings.window.vsync);
+        assert_eq!(settings.window.size.width, 1280);
+        assert_eq!(settings.rendering.msaa_samples, 4);
+        assert_eq!(
+            settings.rendering.power_preference,
+            rendering::PowerPreference::HighPerformance
+        );
+
+        let unchanged = settings.clone();
+        assert!(settings.set("window.colour", "red").is_err());
+        assert!(settings.set("window.vsync", "sometimes").is_err());
+        assert!(settings.set("time.fixed_timestep_hz", "-1").is_err());
+        assert!(settings.set("rendering.msaa_samples", "2").is_err());
+        assert!(settings.apply_args(["--window.title".to_string()]).is_err());
+        assert!(settings
+            .apply_env([("BUTTER_WINDOW__COLOUR".to_string(), "red".to_string())])
+            .is_err());
+        assert_eq!(settings, unchanged);
+    }
+
+    #[test]
+    fn engine_from_settings() {
+        let mut settings = Settings::default();
+        settings.set("window.title", "Game").unwrap();
+        settings.set("time.fixed_timestep_hz", "50").unwrap();
+
+        let engine = ButterEngineBuilder::new()
+            .with_settings(settings)
+            .unwrap()
+            .with_vsync(false)
+            .build();
+        assert_eq!(engine.settings().window.title, "Game");
+        assert!(!engine.settings().window.vsync);
+        assert_eq!(engine.ecs().resource::<Window>().unwrap().title(), "Game");
+        assert_eq!(
+            engine.ecs().resource::<FixedTime>().unwrap().step(),
+            web_time::Duration::from_millis(20)
+        );
+
+        let mut settings = Settings::default();
+        settings.time.fixed_timestep_hz = 0.0;
+        assert!(ButterEngineBuilder::new().with_settings(settings).is_err());
+    }
+}
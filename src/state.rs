@@ -0,0 +1,239 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::ecs::{commands::CommandQueue, system::System, Component, Ecs};
+
+/// A type whose values are the states of the application, usually an enum
+pub trait States: Copy + Eq + Hash + Debug + Send + Sync + 'static {}
+
+impl<S> States for S where S: Copy + Eq + Hash + Debug + Send + Sync + 'static {}
+
+/// A resource giving the current state of type `S`, registered with
+/// [`ButterEngineBuilder::with_state`](crate::ButterEngineBuilder::with_state)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct State<S>(S);
+
+impl<S> State<S>
+where
+    S: States,
+{
+    #[must_use]
+    pub fn get(&self) -> S {
+        self.0
+    }
+}
+
+/// A resource through which systems change the state of type `S`, the
+/// transition happening at the start of the next frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NextState<S>(Option<S>);
+
+impl<S> NextState<S>
+where
+    S: States,
+{
+    pub fn set(&mut self, state: S) {
+        self.0 = Some(state);
+    }
+
+    #[must_use]
+    pub fn get(&self) -> Option<S> {
+        self.0
+    }
+}
+
+/// A component despawning its entity when the application exits the state
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StateScoped<S>(pub S);
+
+impl<S> Component for StateScoped<S> where S: States {}
+
+/// A run condition, for [`run_if`](crate::ecs::system::Into::run_if),
+/// running the system only in the given state
+///
+/// ```
+/// # use butter::{ecs::system::Into, state::in_state, ButterEngineBuilder};
+/// #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// enum GameState {
+///     Menu,
+///     Playing,
+/// }
+///
+/// fn movement() {}
+///
+/// ButterEngineBuilder::new()
+///     .with_state(GameState::Menu)
+///     .with_system(movement.run_if(in_state(GameState::Playing)));
+/// ```
+pub fn in_state<S>(state: S) -> InState<S>
+where
+    S: States,
+{
+    InState {
+        state,
+        command_queue: CommandQueue::new(),
+    }
+}
+
+/// A system telling whether the application is in a state, see [`in_state`]
+pub struct InState<S> {
+    state: S,
+    command_queue: CommandQueue,
+}
+
+impl<S> System<(), bool> for InState<S>
+where
+    S: States,
+{
+    fn run(&mut self, (): (), ecs: &mut Ecs) -> bool {
+        ecs.resource::<State<S>>()
+            .is_some_and(|current_state| current_state.0 == self.state)
+    }
+
+    fn command_queue(&mut self) -> &mut CommandQueue {
+        &mut self.command_queue
+    }
+}
+
+/// The systems run when entering and exiting the states of type `S`, kept as
+/// a non-send resource between the transitions
+pub(crate) struct StateTransitions<S> {
+    on_enter: HashMap<S, Vec<Box<dyn System>>>,
+    on_exit: HashMap<S, Vec<Box<dyn System>>>,
+    entered_initial_state: bool,
+}
+
+impl<S> StateTransitions<S>
+where
+    S: States,
+{
+    pub(crate) fn new() -> Self {
+        Self {
+            on_enter: HashMap::new(),
+            on_exit: HashMap::new(),
+            entered_initial_state: false,
+        }
+    }
+
+    pub(crate) fn add_on_enter(&mut self, state: S, system: Box<dyn System>) {
+        self.on_enter.entry(state).or_default().push(system);
+    }
+
+    pub(crate) fn add_on_exit(&mut self, state: S, system: Box<dyn System>) {
+        self.on_exit.entry(state).or_default().push(system);
+    }
+}
+
+/// Registers the state of type `S`, starting in the given one
+pub(crate) fn insert_state<S>(ecs: &mut Ecs, initial_state: S)
+where
+    S: States,
+{
+    ecs.insert_resource(State(initial_state));
+    ecs.insert_resource(NextState::<S>(None));
+    ecs.insert_non_send_resource(StateTransitions::<S>::new());
+}
+
+/// Enters the initial state the first time it is called, then moves to the
+/// [`NextState`] if it has been set to another state, running the exit and
+/// enter systems and despawning the entities scoped to the exited state
+pub(crate) fn apply_state_transition<S>(ecs: &mut Ecs)
+where
+    S: States,
+{
+    let Some(mut transitions) = ecs.remove_non_send_resource::<StateTransitions<S>>() else {
+        return;
+    };
+    let current_state = ecs.resource::<State<S>>().unwrap().get();
+
+    if !transitions.entered_initial_state {
+        transitions.entered_initial_state = true;
+        if let Some(systems) = transitions.on_enter.get_mut(&current_state) {
+            ecs.run_systems(systems);
+        }
+    }
+
+    let next_state = ecs
+        .resource_mut::<NextState<S>>()
+        .and_then(|next_state| next_state.0.take());
+    if let Some(next_state) = next_state.filter(|&next_state| next_state != current_state) {
+        if let Some(systems) = transitions.on_exit.get_mut(&current_state) {
+            ecs.run_systems(systems);
+        }
+        let scoped_entities: Vec<_> = ecs
+            .entities()
+            .filter(|&entity_index| {
+                ecs.component::<StateScoped<S>>(entity_index)
+                    .is_some_and(|scope| scope.0 == current_state)
+            })
+            .collect();
+        ecs.despawn_batch(scoped_entities);
+
+        ecs.resource_mut::<State<S>>().unwrap().0 = next_state;
+        if let Some(systems) = transitions.on_enter.get_mut(&next_state) {
+            ecs.run_systems(systems);
+        }
+    }
+
+    ecs.insert_non_send_resource(transitions);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ecs::{commands::Commands, resource::ResMut, system::Into},
+        ButterEngineBuilder,
+    };
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    enum GameState {
+        Menu,
+        Playing,
+    }
+
+    #[derive(Default)]
+    struct Log(Vec<&'static str>);
+
+    #[test]
+    fn state_transitions() {
+        fn show_menu(mut log: ResMut<Log>, mut commands: Commands) {
+            log.0.push("show menu");
+            commands.insert((StateScoped(GameState::Menu),));
+        }
+
+        fn start(mut log: ResMut<Log>, mut next_state: ResMut<NextState<GameState>>) {
+            log.0.push("start");
+            next_state.set(GameState::Playing);
+        }
+
+        fn play(mut log: ResMut<Log>) {
+            log.0.push("play");
+        }
+
+        let mut engine = ButterEngineBuilder::new()
+            .with_resource(Log::default())
+            .with_state(GameState::Menu)
+            .with_system_on_enter(GameState::Menu, show_menu)
+            .with_system_on_exit(GameState::Menu, |mut log: ResMut<Log>| {
+                log.0.push("hide menu");
+            })
+            .with_system(start.run_if(in_state(GameState::Menu)))
+            .with_system(play.run_if(in_state(GameState::Playing)))
+            .build();
+        engine.init();
+        assert_eq!(engine.ecs().entity_count(), 1);
+
+        engine.update();
+        engine.update();
+        engine.update();
+        assert_eq!(
+            engine.ecs().resource::<Log>().unwrap().0,
+            ["show menu", "start", "hide menu", "play", "play"]
+        );
+        assert_eq!(
+            engine.ecs().resource::<State<GameState>>().unwrap().get(),
+            GameState::Playing
+        );
+        assert_eq!(engine.ecs().entity_count(), 0);
+    }
+}
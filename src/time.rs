@@ -0,0 +1,596 @@
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use web_time::{Duration, Instant};
+
+/// The timing options of the engine, part of its
+/// [`Settings`](crate::settings::Settings)
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Steps per second of the [`FixedTime`]
+    pub fixed_timestep_hz: f64,
+    /// Initial [scale](Time::set_scale) of the [`Time`]
+    pub scale: f64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            fixed_timestep_hz: 60.0,
+            scale: 1.0,
+        }
+    }
+}
+
+/// Source of the current time, which is the system clock unless it is a
+/// [`Clock::manual`] one advanced by hand, like in tests
+#[derive(Clone, Debug, Default)]
+pub struct Clock {
+    manual: Option<Arc<Mutex<Instant>>>,
+}
+
+impl Clock {
+    #[must_use]
+    pub fn system() -> Self {
+        Self::default()
+    }
+
+    /// A clock which only moves when [`Clock::advance`] is called, shared by
+    /// its clones
+    #[must_use]
+    pub fn manual() -> Self {
+        Self {
+            manual: Some(Arc::new(Mutex::new(Instant::now()))),
+        }
+    }
+
+    /// # Panics
+    ///
+    /// Will panic if another thread panicked while advancing the clock
+    #[must_use]
+    pub fn now(&self) -> Instant {
+        match &self.manual {
+            Some(now) => *now.lock().unwrap(),
+            None => Instant::now(),
+        }
+    }
+
+    /// # Panics
+    ///
+    /// Will panic if the clock isn't a manual one
+    pub fn advance(&self, duration: Duration) {
+        let now = self
+            .manual
+            .as_ref()
+            .expect("Only manual clocks can be advanced");
+        *now.lock().unwrap() += duration;
+    }
+}
+
+/// A resource giving the time of the current frame, updated by the engine
+/// before running the systems
+#[derive(Clone, Debug)]
+pub struct Time {
+    last_update: Option<Instant>,
+    raw_delta: Duration,
+    delta: Duration,
+    elapsed: Duration,
+    frame_count: u64,
+    scale: f64,
+    paused: bool,
+}
+
+impl Time {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            last_update: None,
+            raw_delta: Duration::ZERO,
+            delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            frame_count: 0,
+            scale: 1.0,
+            paused: false,
+        }
+    }
+
+    /// Starts a new frame at `now`, the first one lasting no time
+    pub fn update(&mut self, now: Instant) {
+        self.raw_delta = self.last_update.map_or(Duration::ZERO, |last_update| {
+            now.saturating_duration_since(last_update)
+        });
+        self.last_update = Some(now);
+        self.delta = if self.paused {
+            Duration::ZERO
+        } else {
+            self.raw_delta.mul_f64(self.scale)
+        };
+        self.elapsed += self.delta;
+        self.frame_count += 1;
+    }
+
+    /// Time since the previous frame, scaled and zero while paused
+    #[must_use]
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    #[must_use]
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// Time since the previous frame, ignoring the scale and the pause
+    #[must_use]
+    pub fn raw_delta(&self) -> Duration {
+        self.raw_delta
+    }
+
+    /// Sum of the deltas since the engine started
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    #[must_use]
+    pub fn elapsed_seconds(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+
+    /// Number of updates since the engine started, counting the current one
+    #[must_use]
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    #[must_use]
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Speeds the time up or slows it down, `0.5` meaning slow motion
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the scale is negative or not finite
+    pub fn set_scale(&mut self, scale: f64) {
+        assert!(
+            scale.is_finite() && scale >= 0.0,
+            "The time scale must be positive and finite"
+        );
+        self.scale = scale;
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn unpause(&mut self) {
+        self.paused = false;
+    }
+
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A resource for the systems running at a fixed rate, like physics, which
+/// the engine runs as many times per frame as steps have elapsed
+///
+/// The steps of a frame are capped, 8 by default, so that a slow frame
+/// doesn't make the next ones run even more steps. The time beyond the cap
+/// is dropped and the fixed systems fall behind.
+#[derive(Clone, Debug)]
+pub struct FixedTime {
+    step: Duration,
+    accumulator: Duration,
+    max_steps_per_frame: u32,
+}
+
+impl FixedTime {
+    /// # Panics
+    ///
+    /// Will panic if the step is zero
+    #[must_use]
+    pub fn new(step: Duration) -> Self {
+        assert!(!step.is_zero(), "The fixed time step can't be zero");
+        Self {
+            step,
+            accumulator: Duration::ZERO,
+            max_steps_per_frame: 8,
+        }
+    }
+
+    /// # Panics
+    ///
+    /// Will panic if the rate is not strictly positive and finite
+    #[must_use]
+    pub fn from_hz(hz: f64) -> Self {
+        assert!(
+            hz.is_finite() && hz > 0.0,
+            "The fixed time rate must be strictly positive and finite"
+        );
+        Self::new(Duration::from_secs_f64(1.0 / hz))
+    }
+
+    #[must_use]
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    #[must_use]
+    pub fn step_seconds(&self) -> f32 {
+        self.step.as_secs_f32()
+    }
+
+    #[must_use]
+    pub fn max_steps_per_frame(&self) -> u32 {
+        self.max_steps_per_frame
+    }
+
+    /// # Panics
+    ///
+    /// Will panic if the maximum is zero
+    pub fn set_max_steps_per_frame(&mut self, max_steps_per_frame: u32) {
+        assert!(
+            max_steps_per_frame > 0,
+            "The fixed steps per frame can't be capped to zero"
+        );
+        self.max_steps_per_frame = max_steps_per_frame;
+    }
+
+    /// Adds the time elapsed during a frame, up to the maximum number of
+    /// steps per frame
+    pub fn accumulate(&mut self, delta: Duration) {
+        let max_accumulator = self.step.saturating_mul(self.max_steps_per_frame);
+        self.accumulator = self.accumulator.saturating_add(delta).min(max_accumulator);
+    }
+
+    /// Consumes a step of the accumulated time, returning whether there was
+    /// enough of it
+    pub fn expend(&mut self) -> bool {
+        if let Some(accumulator) = self.accumulator.checked_sub(self.step) {
+            self.accumulator = accumulator;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// How far the accumulated time is into the next step, between 0 and 1,
+    /// to interpolate what is rendered
+    #[must_use]
+    pub fn overstep_fraction(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.step.as_secs_f32()
+    }
+}
+
+impl Default for FixedTime {
+    /// 60 steps per second, as in the default [`Settings`]
+    fn default() -> Self {
+        Self::from_hz(Settings::default().fixed_timestep_hz)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimerMode {
+    #[default]
+    Once,
+    Repeating,
+}
+
+/// Counts down a duration, once or repeatedly
+///
+/// ```
+/// # use butter::time::{Timer, TimerMode};
+/// # use std::time::Duration;
+/// let mut timer = Timer::new(Duration::from_secs(2), TimerMode::Repeating);
+/// timer.tick(Duration::from_secs(5));
+/// assert!(timer.just_finished());
+/// assert_eq!(timer.times_finished_this_tick(), 2);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Timer {
+    duration: Duration,
+    elapsed: Duration,
+    mode: TimerMode,
+    finished: bool,
+    times_finished_this_tick: u32,
+}
+
+impl Timer {
+    #[must_use]
+    pub fn new(duration: Duration, mode: TimerMode) -> Self {
+        Self {
+            duration,
+            mode,
+            ..Self::default()
+        }
+    }
+
+    #[must_use]
+    pub fn from_seconds(seconds: f32, mode: TimerMode) -> Self {
+        Self::new(Duration::from_secs_f32(seconds), mode)
+    }
+
+    /// Advances the timer, usually by [`Time::delta`]
+    pub fn tick(&mut self, delta: Duration) -> &mut Self {
+        self.times_finished_this_tick = 0;
+        if self.mode == TimerMode::Once && self.finished {
+            return self;
+        }
+
+        self.elapsed += delta;
+        if self.elapsed < self.duration {
+            return self;
+        }
+
+        self.finished = true;
+        match self.mode {
+            TimerMode::Once => {
+                self.elapsed = self.duration;
+                self.times_finished_this_tick = 1;
+            }
+            TimerMode::Repeating if self.duration.is_zero() => {
+                self.elapsed = Duration::ZERO;
+                self.times_finished_this_tick = 1;
+            }
+            TimerMode::Repeating => {
+                let times = self.elapsed.as_nanos() / self.duration.as_nanos();
+                self.times_finished_this_tick = u32::try_from(times).unwrap_or(u32::MAX);
+                self.elapsed = Duration::from_nanos(
+                    u64::try_from(self.elapsed.as_nanos() % self.duration.as_nanos())
+                        .unwrap_or_default(),
+                );
+            }
+        }
+        self
+    }
+
+    /// Whether the timer has ever finished, a repeating one included
+    #[must_use]
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Whether the timer finished during the last tick
+    #[must_use]
+    pub fn just_finished(&self) -> bool {
+        self.times_finished_this_tick > 0
+    }
+
+    #[must_use]
+    pub fn times_finished_this_tick(&self) -> u32 {
+        self.times_finished_this_tick
+    }
+
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    #[must_use]
+    pub fn remaining(&self) -> Duration {
+        self.duration.saturating_sub(self.elapsed)
+    }
+
+    /// Elapsed part of the duration, between 0 and 1
+    #[must_use]
+    pub fn fraction(&self) -> f32 {
+        if self.duration.is_zero() {
+            1.0
+        } else {
+            self.elapsed.as_secs_f32() / self.duration.as_secs_f32()
+        }
+    }
+
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.duration = duration;
+    }
+
+    #[must_use]
+    pub fn mode(&self) -> TimerMode {
+        self.mode
+    }
+
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+        self.finished = false;
+        self.times_finished_this_tick = 0;
+    }
+}
+
+/// Measures the time elapsed while it isn't paused
+#[derive(Clone, Debug, Default)]
+pub struct Stopwatch {
+    elapsed: Duration,
+    paused: bool,
+}
+
+impl Stopwatch {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tick(&mut self, delta: Duration) -> &mut Self {
+        if !self.paused {
+            self.elapsed += delta;
+        }
+        self
+    }
+
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    #[must_use]
+    pub fn elapsed_seconds(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn unpause(&mut self) {
+        self.paused = false;
+    }
+
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ecs::{
+            resource::{Res, ResMut},
+            system::Local,
+        },
+        ButterEngineBuilder,
+    };
+
+    const FRAME: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn time_scale_and_pause() {
+        let clock = Clock::manual();
+        let mut time = Time::new();
+        time.update(clock.now());
+        assert_eq!(time.delta(), Duration::ZERO);
+
+        clock.advance(FRAME);
+        time.set_scale(0.5);
+        time.update(clock.now());
+        assert_eq!(time.delta(), FRAME / 2);
+        assert_eq!(time.raw_delta(), FRAME);
+
+        clock.advance(FRAME);
+        time.pause();
+        time.update(clock.now());
+        assert_eq!(time.delta(), Duration::ZERO);
+        assert_eq!(time.elapsed(), FRAME / 2);
+        assert_eq!(time.frame_count(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "must be strictly positive and finite")]
+    fn fixed_time_zero_rate() {
+        let _ = FixedTime::from_hz(0.0);
+    }
+
+    #[test]
+    fn fixed_time_steps() {
+        let mut fixed_time = FixedTime::new(FRAME);
+        fixed_time.accumulate(FRAME * 5 / 2);
+        assert!(fixed_time.expend());
+        assert!(fixed_time.expend());
+        assert!(!fixed_time.expend());
+        assert!((fixed_time.overstep_fraction() - 0.5).abs() < 1e-4);
+
+        // A long frame runs at most the maximum number of steps
+        fixed_time.set_max_steps_per_frame(3);
+        fixed_time.accumulate(FRAME * 100);
+        assert_eq!(
+            std::iter::from_fn(|| fixed_time.expend().then_some(())).count(),
+            3
+        );
+        fixed_time.accumulate(FRAME);
+        assert_eq!(
+            std::iter::from_fn(|| fixed_time.expend().then_some(())).count(),
+            1
+        );
+    }
+
+    #[test]
+    fn timers() {
+        let mut once = Timer::new(FRAME * 2, TimerMode::Once);
+        assert!(!once.tick(FRAME).just_finished());
+        assert!(once.tick(FRAME * 3).just_finished());
+        assert!(!once.tick(FRAME).just_finished());
+        assert!(once.finished());
+        assert_eq!(once.remaining(), Duration::ZERO);
+
+        let mut repeating = Timer::new(FRAME * 2, TimerMode::Repeating);
+        repeating.tick(FRAME * 5);
+        assert_eq!(repeating.times_finished_this_tick(), 2);
+        assert_eq!(repeating.elapsed(), FRAME);
+
+        let mut stopwatch = Stopwatch::new();
+        stopwatch.tick(FRAME);
+        stopwatch.pause();
+        stopwatch.tick(FRAME);
+        assert_eq!(stopwatch.elapsed(), FRAME);
+    }
+
+    #[test]
+    fn engine_updates_time() {
+        struct Steps(u32);
+
+        #[allow(clippy::needless_pass_by_value)]
+        fn step(mut steps: ResMut<Steps>, fixed_time: Res<FixedTime>) {
+            assert_eq!(fixed_time.step(), FRAME);
+            steps.0 += 1;
+        }
+
+        #[allow(clippy::needless_pass_by_value)]
+        fn check_time(time: Res<Time>, mut frame: Local<u32>) {
+            *frame += 1;
+            let expected = if *frame == 1 {
+                Duration::ZERO
+            } else {
+                FRAME * 3
+            };
+            assert_eq!(time.delta(), expected);
+        }
+
+        let clock = Clock::manual();
+        let mut engine = ButterEngineBuilder::new()
+            .with_clock(clock.clone())
+            .with_resource(FixedTime::new(FRAME))
+            .with_resource(Steps(0))
+            .with_system(check_time)
+            .with_fixed_system(step)
+            .build();
+        engine.init();
+        engine.update();
+        clock.advance(FRAME * 3);
+        engine.update();
+
+        assert_eq!(engine.ecs().resource::<Steps>().unwrap().0, 3);
+        assert_eq!(engine.ecs().resource::<Time>().unwrap().frame_count(), 2);
+
+        // The timings cover every stage of the last frame
+        let timings = engine.ecs().system_timings();
+        let runs = |system_name: &str| {
+            timings
+                .iter()
+                .filter(|timing| timing.name == system_name)
+                .count()
+        };
+        assert_eq!(runs(std::any::type_name_of_val(&step)), 3);
+        assert_eq!(runs(std::any::type_name_of_val(&check_time)), 1);
+    }
+}
@@ -1,6 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::{Component, EntityIndex};
+
+pub use winit::window::CursorGrabMode;
+
+/// The window options of the engine, part of its
+/// [`Settings`](crate::settings::Settings)
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
     pub title: String,
     pub size: Size,
+    pub mode: WindowMode,
+    pub resizable: bool,
+    /// Whether the frames wait for the refresh of the screen, avoiding
+    /// tearing at the cost of latency
+    pub vsync: bool,
     /// The canvas used in the wasm build
     pub wasm_canvas_id: String,
 }
@@ -10,12 +25,15 @@ impl Default for Settings {
         Self {
             title: String::from("Butter application"),
             size: Size::default(),
-            wasm_canvas_id: String::from("butter-application"),
+            mode: WindowMode::default(),
+            resizable: true,
+            vsync: true,
+            wasm_canvas_id: String::from("butter-app"),
         }
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Size {
     pub width: u32,
     pub height: u32,
@@ -29,3 +47,235 @@ impl Default for Size {
         }
     }
 }
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WindowMode {
+    #[default]
+    Windowed,
+    /// Covers the screen without changing its resolution
+    BorderlessFullscreen,
+    /// Takes exclusive control of the screen, in its best video mode
+    Fullscreen,
+}
+
+/// Describes a window, which the runner keeps in sync with the actual window
+///
+/// The primary window, created from the [`Settings`], is the `Window`
+/// resource. Other windows are opened by spawning entities with a `Window`
+/// component and closed by despawning them, which the runner also does when
+/// they are asked to close, while closing the primary window exits. The
+/// changes made by the systems are applied at the end of the frame.
+#[derive(Clone, Debug, PartialEq, Component)]
+pub struct Window {
+    title: String,
+    size: Size,
+    scale_factor: f64,
+    mode: WindowMode,
+    resizable: bool,
+    vsync: bool,
+    cursor_visible: bool,
+    cursor_grab: CursorGrabMode,
+}
+
+impl Window {
+    #[must_use]
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            title: settings.title.clone(),
+            size: settings.size,
+            scale_factor: 1.0,
+            mode: settings.mode,
+            resizable: settings.resizable,
+            vsync: settings.vsync,
+            cursor_visible: true,
+            cursor_grab: CursorGrabMode::None,
+        }
+    }
+
+    #[must_use]
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn set_title(&mut self, title: impl Into<String>) {
+        self.title = title.into();
+    }
+
+    /// In physical pixels
+    #[must_use]
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    /// Requests a new size, which the platform may not respect, the actual
+    /// size being given by the [`WindowResized`] events
+    pub fn set_size(&mut self, size: Size) {
+        self.size = size;
+    }
+
+    /// The number of physical pixels per logical pixel
+    #[must_use]
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    #[must_use]
+    pub fn mode(&self) -> WindowMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: WindowMode) {
+        self.mode = mode;
+    }
+
+    #[must_use]
+    pub fn resizable(&self) -> bool {
+        self.resizable
+    }
+
+    pub fn set_resizable(&mut self, resizable: bool) {
+        self.resizable = resizable;
+    }
+
+    #[must_use]
+    pub fn vsync(&self) -> bool {
+        self.vsync
+    }
+
+    /// Whether the frames wait for the refresh of the screen, see
+    /// [`Settings::vsync`]
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.vsync = vsync;
+    }
+
+    #[must_use]
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    pub fn set_cursor_visible(&mut self, cursor_visible: bool) {
+        self.cursor_visible = cursor_visible;
+    }
+
+    #[must_use]
+    pub fn cursor_grab(&self) -> CursorGrabMode {
+        self.cursor_grab
+    }
+
+    /// Keeps the cursor in the window, or locks it in place for camera
+    /// controls, when the platform supports it
+    pub fn set_cursor_grab(&mut self, cursor_grab: CursorGrabMode) {
+        self.cursor_grab = cursor_grab;
+    }
+
+    /// Updates the window after the platform resized it, which doesn't count
+    /// as a change to apply
+    pub(crate) fn resized(&mut self, size: Size, scale_factor: f64) {
+        self.size = size;
+        self.scale_factor = scale_factor;
+    }
+}
+
+/// Identifies the window the window and cursor events come from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WindowId {
+    /// The window of the [`Window`] resource
+    Primary,
+    /// The window of an entity with a [`Window`] component
+    Entity(EntityIndex),
+}
+
+/// Event sent when a window is resized
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WindowResized {
+    pub window: WindowId,
+    /// In physical pixels
+    pub size: Size,
+}
+
+/// Event sent when a window moves to a screen with another pixel density,
+/// or the density of the screen changes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WindowScaleFactorChanged {
+    pub window: WindowId,
+    pub scale_factor: f64,
+}
+
+/// Event sent when a window gains or loses the focus of the keyboard
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WindowFocused {
+    pub window: WindowId,
+    pub focused: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ecs::{event::EventReader, resource::ResMut},
+        ButterEngineBuilder,
+    };
+
+    #[test]
+    fn window_resource() {
+        fn hide_cursor(mut window: ResMut<Window>) {
+            window.set_vsync(false);
+            window.set_cursor_visible(false);
+            window.set_cursor_grab(CursorGrabMode::Locked);
+        }
+
+        let mut engine = ButterEngineBuilder::new()
+            .with_window_size(Size {
+                width: 1280,
+                height: 720,
+            })
+            .with_window_mode(WindowMode::BorderlessFullscreen)
+            .with_system(hide_cursor)
+            .build();
+        engine.init();
+        engine.update();
+
+        let window = engine.ecs().resource::<Window>().unwrap();
+        assert_eq!(window.size().width, 1280);
+        assert_eq!(window.mode(), WindowMode::BorderlessFullscreen);
+        assert!(!window.vsync());
+        assert!(!window.cursor_visible());
+        assert_eq!(window.cursor_grab(), CursorGrabMode::Locked);
+    }
+
+    #[test]
+    fn window_entities() {
+        #[derive(Default)]
+        struct Resized(Vec<WindowId>);
+
+        fn record_resized(mut resized: ResMut<Resized>, mut events: EventReader<WindowResized>) {
+            resized.0.extend(events.read().map(|event| event.window));
+        }
+
+        let mut engine = ButterEngineBuilder::new()
+            .with_resource(Resized::default())
+            .with_system(record_resized)
+            .build();
+        engine.init();
+        let entity_index = engine.ecs_mut().insert((Window::new(&Settings {
+            title: String::from("Map"),
+            ..Settings::default()
+        }),));
+        let size = Size {
+            width: 640,
+            height: 480,
+        };
+        engine.resize(WindowId::Entity(entity_index), size, 1.0);
+        engine.update();
+
+        let ecs = engine.ecs();
+        let window = ecs.component::<Window>(entity_index).unwrap();
+        assert_eq!(window.title(), "Map");
+        assert_eq!(window.size(), size);
+        assert_eq!(ecs.resource::<Window>().unwrap().size(), Size::default());
+        assert_eq!(
+            ecs.resource::<Resized>().unwrap().0,
+            [WindowId::Entity(entity_index)]
+        );
+    }
+}
@@ -1,81 +1,402 @@
+use std::collections::{HashMap, HashSet};
+
+use butter_math::vector::Vector2;
 use pollster;
 use winit::{
     dpi::{PhysicalSize, Size},
-    event::{Event, WindowEvent},
-    event_loop::EventLoop,
-    window::WindowBuilder,
+    event::{DeviceEvent, ElementState, Event, MouseScrollDelta, WindowEvent},
+    event_loop::{EventLoop, EventLoopWindowTarget},
+    monitor::MonitorHandle,
+    window::{Fullscreen, WindowBuilder},
 };
 
-use crate::ButterEngine;
+use crate::{
+    input::{
+        mouse::MouseScrollUnit, ButtonState, KeyboardInput, MouseButtonInput, MouseMotion,
+        MouseWheel, ReceivedCharacter,
+    },
+    lifecycle::AppLifecycle,
+    window::{Window, WindowFocused, WindowId, WindowMode},
+    AppExit, ButterEngine,
+};
 
 pub struct ButterRunner;
 impl ButterRunner {
-    /// Starts the engine with the given window settings
+    /// Starts the engine in a primary window created from its settings and
+    /// kept in sync with the [`Window`] resource, opening and closing the
+    /// other windows as the entities with a [`Window`] component come and go
     ///
     /// # Panics
     ///
     /// This may panic if the window creation fails
     pub fn run(mut engine: ButterEngine) {
-        #[cfg(target_arch = "wasm32")]
-        use winit::platform::web::WindowBuilderExtWebSys;
-
         let event_loop = EventLoop::new();
 
-        let window_settings = &engine.settings().window_settings;
-        #[allow(unused_mut)]
-        let mut window_builder = WindowBuilder::new()
-            .with_inner_size(Size::Physical(PhysicalSize {
-                width: window_settings.size.width,
-                height: window_settings.size.height,
-            }))
-            .with_title(&window_settings.title)
-            .with_resizable(false);
-
-        #[cfg(target_arch = "wasm32")]
-        {
-            use wasm_bindgen::JsCast;
-
-            std::panic::set_hook(Box::new(console_error_panic_hook::hook));
-
-            let web_window = web_sys::window().unwrap();
-            let document = web_window.document().unwrap();
-
-            let canvas_id = &window_settings.wasm_canvas_id;
-            let canvas = document
-                .get_element_by_id(canvas_id)
-                .unwrap()
-                .dyn_into::<web_sys::HtmlCanvasElement>()
-                .ok();
-            window_builder = window_builder.with_canvas(canvas);
-        }
-
-        let window = window_builder.build(&event_loop).unwrap();
+        let settings = engine.settings();
+        let primary_window = engine
+            .ecs()
+            .resource::<Window>()
+            .cloned()
+            .unwrap_or_else(|| Window::new(&settings.window));
+        let window = create_window(
+            &event_loop,
+            &primary_window,
+            Some(&settings.window.wasm_canvas_id),
+        );
         let window_size = window.inner_size();
-        engine.set_graphic_state(pollster::block_on(crate::graphics::State::new(
+        let (graphic_state, surface) = pollster::block_on(crate::graphics::State::new(
             &window,
             (window_size.width, window_size.height),
-        )));
+            primary_window.vsync(),
+            settings.rendering.graphics_options(),
+        ));
+        engine.set_graphic_state(graphic_state);
+        engine.add_surface(WindowId::Primary, surface);
+        let mut windows = Windows::default();
+        windows.open(&mut engine, WindowId::Primary, window);
         engine.init();
+        windows.sync(&mut engine, &event_loop);
 
-        event_loop.run(move |event, _, control_flow| {
+        event_loop.run(move |event, event_loop, control_flow| {
             control_flow.set_poll();
 
             match event {
                 Event::WindowEvent {
                     event: WindowEvent::CloseRequested,
-                    ..
+                    window_id,
+                } => match windows.id(window_id) {
+                    Some(WindowId::Primary) => engine.send_event(AppExit),
+                    Some(WindowId::Entity(entity_index)) => {
+                        engine.ecs_mut().delete(entity_index);
+                        windows.sync(&mut engine, event_loop);
+                    }
+                    None => {}
+                },
+                Event::Suspended => engine.send_event(AppLifecycle::Suspended),
+                Event::Resumed => engine.send_event(AppLifecycle::Resumed),
+                Event::RedrawRequested(window_id) => {
+                    if let Some(id) = windows.id(window_id) {
+                        engine.render(id);
+                    }
+                }
+                Event::WindowEvent {
+                    event: WindowEvent::Resized(size),
+                    window_id,
+                } => {
+                    if let Some(id) = windows.id(window_id) {
+                        windows.resize(&mut engine, id, size, None);
+                    }
+                }
+                Event::WindowEvent {
+                    event:
+                        WindowEvent::ScaleFactorChanged {
+                            scale_factor,
+                            new_inner_size,
+                        },
+                    window_id,
                 } => {
-                    control_flow.set_exit();
+                    if let Some(id) = windows.id(window_id) {
+                        windows.resize(&mut engine, id, *new_inner_size, Some(scale_factor));
+                    }
+                }
+                Event::WindowEvent { event, window_id } => {
+                    if let Some(id) = windows.id(window_id) {
+                        forward_window_event(&mut engine, id, &event);
+                    }
                 }
-                Event::RedrawRequested(window_id) if window_id == window.id() => {
-                    engine.render();
+                Event::DeviceEvent {
+                    event: DeviceEvent::MouseMotion { delta: (x, y) },
+                    ..
+                } => {
+                    #[allow(clippy::cast_possible_truncation)]
+                    engine.send_event(MouseMotion {
+                        delta: Vector2::new(x as f32, y as f32),
+                    });
                 }
                 Event::MainEventsCleared => {
                     engine.update();
-                    window.request_redraw();
+                    if engine.should_exit() {
+                        engine.shutdown();
+                        control_flow.set_exit();
+                        return;
+                    }
+                    windows.sync(&mut engine, event_loop);
+                    windows.request_redraws();
                 }
                 _ => {}
             }
         });
     }
 }
+
+/// A window opened by the runner, with the state of its [`Window`] as last
+/// applied to find what the systems changed
+struct OpenWindow {
+    window: winit::window::Window,
+    applied: Window,
+}
+
+/// The windows opened by the runner
+#[derive(Default)]
+struct Windows {
+    open: HashMap<WindowId, OpenWindow>,
+    ids: HashMap<winit::window::WindowId, WindowId>,
+}
+
+impl Windows {
+    fn id(&self, window_id: winit::window::WindowId) -> Option<WindowId> {
+        self.ids.get(&window_id).copied()
+    }
+
+    /// Tracks the window, whose surface has been added to the engine, and
+    /// reports its actual size
+    fn open(&mut self, engine: &mut ButterEngine, id: WindowId, window: winit::window::Window) {
+        engine.resize(
+            id,
+            physical_size(window.inner_size()),
+            window.scale_factor(),
+        );
+        let applied = engine
+            .window(id)
+            .cloned()
+            .unwrap_or_else(|| Window::new(&engine.settings().window));
+        self.ids.insert(window.id(), id);
+        self.open.insert(id, OpenWindow { window, applied });
+    }
+
+    /// Closes the windows of the despawned entities, opens the windows of
+    /// the new ones and applies the changes made to the others
+    fn sync(&mut self, engine: &mut ButterEngine, event_loop: &EventLoopWindowTarget<()>) {
+        let entity_windows: HashSet<WindowId> = engine
+            .ecs()
+            .entities()
+            .filter(|&entity_index| engine.ecs().component::<Window>(entity_index).is_some())
+            .map(WindowId::Entity)
+            .collect();
+
+        let closed: Vec<WindowId> = self
+            .open
+            .keys()
+            .filter(|id| **id != WindowId::Primary && !entity_windows.contains(id))
+            .copied()
+            .collect();
+        for id in closed {
+            // The surface goes before its window
+            engine.remove_surface(id);
+            if let Some(open_window) = self.open.remove(&id) {
+                self.ids.remove(&open_window.window.id());
+            }
+        }
+
+        for &id in &entity_windows {
+            if self.open.contains_key(&id) {
+                continue;
+            }
+            let Some(description) = engine.window(id).cloned() else {
+                continue;
+            };
+            let window = create_window(event_loop, &description, None);
+            let size = window.inner_size();
+            if let Some(graphic_state) = engine.graphic_state() {
+                let surface = graphic_state.create_surface(
+                    &window,
+                    (size.width, size.height),
+                    description.vsync(),
+                );
+                engine.add_surface(id, surface);
+            }
+            self.open(engine, id, window);
+        }
+
+        for (&id, open_window) in &mut self.open {
+            let Some(changed_window) = engine.window(id) else {
+                continue;
+            };
+            let vsync_changed = changed_window.vsync() != open_window.applied.vsync();
+            apply_window_changes(
+                &open_window.window,
+                &mut open_window.applied,
+                changed_window,
+            );
+            if vsync_changed {
+                engine.set_vsync(id, open_window.applied.vsync());
+            }
+        }
+    }
+
+    /// Forwards the new size of the window to the engine, which its state
+    /// then already has
+    fn resize(
+        &mut self,
+        engine: &mut ButterEngine,
+        id: WindowId,
+        size: PhysicalSize<u32>,
+        scale_factor: Option<f64>,
+    ) {
+        let Some(open_window) = self.open.get_mut(&id) else {
+            return;
+        };
+        let scale_factor = scale_factor.unwrap_or_else(|| open_window.window.scale_factor());
+        engine.resize(id, physical_size(size), scale_factor);
+        if let Some(resized_window) = engine.window(id) {
+            open_window.applied.clone_from(resized_window);
+        }
+    }
+
+    fn request_redraws(&self) {
+        for open_window in self.open.values() {
+            open_window.window.request_redraw();
+        }
+    }
+}
+
+/// Creates a window as described, on the canvas of the page in the wasm build
+/// when one is given
+///
+/// # Panics
+///
+/// This may panic if the window creation fails
+fn create_window(
+    event_loop: &EventLoopWindowTarget<()>,
+    description: &Window,
+    #[allow(unused_variables)] wasm_canvas_id: Option<&str>,
+) -> winit::window::Window {
+    #[cfg(target_arch = "wasm32")]
+    use winit::platform::web::WindowBuilderExtWebSys;
+
+    let size = description.size();
+    #[allow(unused_mut)]
+    let mut window_builder = WindowBuilder::new()
+        .with_inner_size(Size::Physical(PhysicalSize {
+            width: size.width,
+            height: size.height,
+        }))
+        .with_title(description.title())
+        .with_resizable(description.resizable())
+        .with_fullscreen(fullscreen(description.mode(), event_loop.primary_monitor()));
+
+    #[cfg(target_arch = "wasm32")]
+    if let Some(canvas_id) = wasm_canvas_id {
+        use wasm_bindgen::JsCast;
+
+        std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+
+        let web_window = web_sys::window().unwrap();
+        let document = web_window.document().unwrap();
+
+        let canvas = document
+            .get_element_by_id(canvas_id)
+            .unwrap()
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .ok();
+        window_builder = window_builder.with_canvas(canvas);
+    }
+
+    let window = window_builder.build(event_loop).unwrap();
+    window.set_cursor_visible(description.cursor_visible());
+    // Not every platform supports every grab mode, the cursor is then left
+    // free
+    let _ = window.set_cursor_grab(description.cursor_grab());
+    window
+}
+
+fn physical_size(size: PhysicalSize<u32>) -> crate::window::Size {
+    crate::window::Size {
+        width: size.width,
+        height: size.height,
+    }
+}
+
+fn fullscreen(mode: WindowMode, monitor: Option<MonitorHandle>) -> Option<Fullscreen> {
+    match mode {
+        WindowMode::Windowed => None,
+        WindowMode::BorderlessFullscreen => Some(Fullscreen::Borderless(monitor)),
+        WindowMode::Fullscreen => monitor
+            .and_then(|monitor| {
+                monitor.video_modes().max_by_key(|video_mode| {
+                    let size = video_mode.size();
+                    (
+                        size.width * size.height,
+                        video_mode.refresh_rate_millihertz(),
+                    )
+                })
+            })
+            .map(Fullscreen::Exclusive),
+    }
+}
+
+/// Applies to the window the changes made by the systems to its [`Window`]
+/// since the last frame, except the vsync which is applied to its surface
+fn apply_window_changes(
+    window: &winit::window::Window,
+    applied_window: &mut Window,
+    changed_window: &Window,
+) {
+    if changed_window.title() != applied_window.title() {
+        window.set_title(changed_window.title());
+    }
+    if changed_window.size() != applied_window.size() {
+        let size = changed_window.size();
+        window.set_inner_size(PhysicalSize::new(size.width, size.height));
+    }
+    if changed_window.mode() != applied_window.mode() {
+        window.set_fullscreen(fullscreen(changed_window.mode(), window.current_monitor()));
+    }
+    if changed_window.resizable() != applied_window.resizable() {
+        window.set_resizable(changed_window.resizable());
+    }
+    if changed_window.cursor_visible() != applied_window.cursor_visible() {
+        window.set_cursor_visible(changed_window.cursor_visible());
+    }
+    if changed_window.cursor_grab() != applied_window.cursor_grab() {
+        // Not every platform supports every grab mode, the cursor is then
+        // left free
+        let _ = window.set_cursor_grab(changed_window.cursor_grab());
+    }
+    applied_window.clone_from(changed_window);
+}
+
+/// Sends the input events of the window to the engine
+#[allow(clippy::cast_possible_truncation)]
+fn forward_window_event(engine: &mut ButterEngine, window: WindowId, event: &WindowEvent) {
+    match *event {
+        WindowEvent::KeyboardInput { input, .. } => engine.send_event(KeyboardInput {
+            key_code: input.virtual_keycode,
+            scan_code: input.scancode,
+            state: button_state(input.state),
+        }),
+        WindowEvent::ReceivedCharacter(character) => {
+            engine.send_event(ReceivedCharacter(character));
+        }
+        WindowEvent::MouseInput { state, button, .. } => engine.send_event(MouseButtonInput {
+            button,
+            state: button_state(state),
+        }),
+        WindowEvent::CursorMoved { position, .. } => {
+            engine.move_cursor(
+                window,
+                Some(Vector2::new(position.x as f32, position.y as f32)),
+            );
+        }
+        WindowEvent::CursorLeft { .. } => engine.move_cursor(window, None),
+        WindowEvent::Focused(focused) => engine.send_event(WindowFocused { window, focused }),
+        WindowEvent::MouseWheel { delta, .. } => engine.send_event(match delta {
+            MouseScrollDelta::LineDelta(x, y) => MouseWheel {
+                delta: Vector2::new(x, y),
+                unit: MouseScrollUnit::Line,
+            },
+            MouseScrollDelta::PixelDelta(position) => MouseWheel {
+                delta: Vector2::new(position.x as f32, position.y as f32),
+                unit: MouseScrollUnit::Pixel,
+            },
+        }),
+        _ => {}
+    }
+}
+
+fn button_state(state: ElementState) -> ButtonState {
+    match state {
+        ElementState::Pressed => ButtonState::Pressed,
+        ElementState::Released => ButtonState::Released,
+    }
+}